@@ -32,7 +32,7 @@ export function InstructionInput({ value, setValue }: InstructionInputProps) {
             onChange={(e) => setValue(e.target.value)}
             rows={4}
             className="instruction-textarea"
-            placeholder="Exemple : réponds comme un expert Rust, ne fais pas d'hypothèses hors code source"
+            placeholder="Exemple : réponds comme un expert Rust, ne fais pas d'hypothèses hors code source (vide : instructions du .deepwiki.toml du dépôt)"
           />
         )}
       </div>
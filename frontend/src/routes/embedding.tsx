@@ -1,6 +1,16 @@
 import { createFileRoute, useNavigate } from "@tanstack/react-router";
 import { useEffect, useState } from "react";
-import { getIndexableRepos, scanRepo } from "../service";
+import {
+  cancelScanJob,
+  estimateScan,
+  getIndexableRepos,
+  getScanJob,
+  scanRepo,
+  type FilterConfig,
+  type FilterRule,
+  type ScanEstimate,
+  type ScanJob,
+} from "../service";
 
 export const Route = createFileRoute("/embedding")({
   component: Embedding,
@@ -13,6 +23,10 @@ function Embedding() {
   const [repoIdentifier, setRepoIdentifier] = useState("");
   const [filterMode, setFilterMode] = useState<"include" | "exclude">("exclude");
   const [filterPaths, setFilterPaths] = useState("");
+  const [gitRef, setGitRef] = useState("");
+  const [job, setJob] = useState<ScanJob | null>(null);
+  const [estimate, setEstimate] = useState<ScanEstimate | null>(null);
+  const [estimating, setEstimating] = useState(false);
   const navigate = useNavigate()
 
   const fetchRepos = async () => {
@@ -29,22 +43,61 @@ function Embedding() {
     fetchRepos();
   }, []);
 
+  useEffect(() => {
+    if (!job) return;
+    if (job.phase === "done") {
+      navigate({to: `/ask/${job.repo}`});
+      return;
+    }
+    if (["partial", "failed", "cancelled"].includes(job.phase)) return;
+
+    const timer = setTimeout(() => {
+      getScanJob(job.id).then(setJob).catch((err) => setError(String(err)));
+    }, 1000);
+    return () => clearTimeout(timer);
+  }, [job, navigate]);
+
+  // Une ligne par règle : "!" inverse le mode, "re:" pour une regex au lieu d'un glob
+  const buildFilterConfig = (): FilterConfig | undefined => {
+    const inverse = filterMode === "exclude" ? "include" : "exclude";
+    const rules: FilterRule[] = filterPaths
+      .split('\n')
+      .map(p => p.trim())
+      .filter(p => p.length > 0)
+      .map((line): FilterRule => {
+        const negated = line.startsWith("!");
+        const pattern = negated ? line.slice(1).trim() : line;
+        const regex = pattern.startsWith("re:");
+        return {
+          mode: negated ? inverse : filterMode,
+          pattern: regex ? pattern.slice(3) : pattern,
+          syntax: regex ? "regex" : "glob",
+        };
+      });
+
+    return rules.length > 0 ? { rules } : undefined;
+  };
+
+  const handleEstimate = () => {
+    if (!repoIdentifier) return;
+
+    setError(null);
+    setEstimating(true);
+    estimateScan(repoIdentifier, buildFilterConfig(), gitRef.trim() || undefined)
+      .then(setEstimate)
+      .catch((err) => setError(String(err)))
+      .finally(() => setEstimating(false));
+  };
+
   const handleSubmit = (e: React.FormEvent) => {
     e.preventDefault();
     if (!repoIdentifier) return;
     
-    const paths = filterPaths.trim() 
-      ? filterPaths.split('\n').map(p => p.trim()).filter(p => p.length > 0)
-      : [];
-      
-    const filterConfig = paths.length > 0 ? {
-      mode: filterMode,
-      paths: paths
-    } : undefined;
-    
-    scanRepo(repoIdentifier, filterConfig).then(res => {
-      navigate({to: `/ask/${res.repo_identifier}`})
-    })
+    setError(null);
+    scanRepo(repoIdentifier, buildFilterConfig(), gitRef.trim() || undefined)
+      .then(res => getScanJob(res.job_id))
+      .then(setJob)
+      .catch((err) => setError(String(err)));
   };
 
   return (
@@ -101,22 +154,35 @@ function Embedding() {
             </div>
           </div>
           
+          <div className="filter-paths">
+            <label htmlFor="gitRef">Branche, tag ou commit (optionnel) :</label>
+            <input
+              id="gitRef"
+              value={gitRef}
+              onChange={(e) => setGitRef(e.target.value)}
+              placeholder="main, v1.2.0, 3f2a1c…"
+            />
+            <small>Vide : indexe les fichiers actuellement présents dans /clone.</small>
+          </div>
+
           <div className="filter-paths">
             <label htmlFor="filterPaths">
-              {filterMode === "exclude" ? "Chemins à exclure" : "Chemins à inclure"} (un par ligne) :
+              {filterMode === "exclude" ? "Chemins à exclure" : "Chemins à inclure"} (une règle par ligne) :
             </label>
             <textarea
               id="filterPaths"
               value={filterPaths}
               onChange={(e) => setFilterPaths(e.target.value)}
               placeholder={filterMode === "exclude" 
-                ? "test\n.test.\nlegacy\nvendor"
-                : "src/\ndocs/\n.rs\n.ts"
+                ? "**/generated/**\n*_test.go\nvendor/\n!vendor/ours/**"
+                : "services/**\ndocs/\n!**/generated/**\nre:\\.test\\.js$"
               }
               rows={6}
             />
             <small>
-              Supporte les regexs. <br/> Note: .gitignore est déjà respecté automatiquement
+              Globs façon .gitignore, relatifs à la racine du dépôt ; <code>re:</code> pour une regex,
+              <code>!</code> pour inverser le mode. La dernière règle qui correspond l'emporte. <br/>
+              Note: .gitignore est déjà respecté automatiquement
             </small>
           </div>
         </section>
@@ -124,9 +190,53 @@ function Embedding() {
 
       {repoIdentifier && (
         <form onSubmit={handleSubmit} className="repo-form">
-          <button type="submit">Scanner : {repoIdentifier}</button>
+          <button type="submit" disabled={job !== null && !["done", "partial", "failed", "cancelled"].includes(job.phase)}>
+            Scanner : {repoIdentifier}
+          </button>
+          <button type="button" onClick={handleEstimate} disabled={estimating}>
+            {estimating ? "Estimation…" : "Estimer le coût"}
+          </button>
         </form>
       )}
+
+      {estimate && (
+        <section className="estimate-section">
+          <h2>Estimation pour {estimate.repo}</h2>
+          <p>
+            {estimate.files} fichiers, {estimate.chunks} chunks, {estimate.tokens} tokens
+            ({estimate.files_skipped.length} fichiers ignorés, {estimate.chunks_deduplicated} chunks
+            dédupliqués,{" "}
+            {Object.values(estimate.secrets_redacted).reduce((a, b) => a + b, 0)} secrets masqués)
+          </p>
+          <ul>
+            {estimate.costs.map((cost) => (
+              <li key={`${cost.provider}/${cost.model}`} className={cost.configured ? "selected" : ""}>
+                {cost.provider} / {cost.model} :{" "}
+                {cost.cost_usd === null ? "prix inconnu" : `$${cost.cost_usd.toFixed(4)}`}
+                {cost.configured && " (configuré)"}
+              </li>
+            ))}
+          </ul>
+        </section>
+      )}
+
+      {job && (
+        <section className="job-section">
+          <h2>Indexation de {job.repo} : {job.phase}</h2>
+          <p>Fichiers : {job.files_done} / {job.files_total}</p>
+          <p>Chunks : {job.chunks_done} / {job.chunks_total}</p>
+          {job.errors.length > 0 && (
+            <ul className="error">
+              {job.errors.map((err, i) => <li key={i}>{err}</li>)}
+            </ul>
+          )}
+          {!["done", "partial", "failed", "cancelled"].includes(job.phase) && (
+            <button type="button" onClick={() => cancelScanJob(job.id).then(setJob)}>
+              Annuler
+            </button>
+          )}
+        </section>
+      )}
     </div>
   );
 }
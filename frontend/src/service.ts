@@ -7,13 +7,85 @@ const api = axios.create({
 });
 
 // Types
+export type FilterMode = "include" | "exclude";
+
+// Rules are matched in order against the repo-relative path, the last matching one wins
+export interface FilterRule {
+  mode: FilterMode;
+  pattern: string;
+  syntax?: "glob" | "regex";
+}
+
 export interface FilterConfig {
-  mode: "include" | "exclude";
-  paths: string[];
+  rules: FilterRule[];
 }
 
 export interface ScanResponse {
   repo_identifier: string;
+  job_id: string;
+}
+
+export type ScanPhase =
+  | "queued"
+  | "parsing"
+  | "chunking"
+  | "embedding"
+  | "upserting"
+  | "done"
+  | "partial"
+  | "failed"
+  | "cancelled";
+
+export interface ScanReport {
+  repo: string;
+  files_seen: number;
+  files_indexed: number;
+  files_skipped: { path: string; reason: string }[];
+  chunks_per_language: Record<string, number>;
+  chunks_per_type: Record<string, number>;
+  chunks_embedded: number;
+  chunks_deduplicated: number;
+  chunks_skipped: { path: string; chunk_index: string; reason: string }[];
+  secrets_redacted: Record<string, number>;
+  failed_batches: { index: number; chunks: number; error: string }[];
+  estimated_tokens: number;
+  actual_tokens: number;
+  duration_secs: number;
+}
+
+export interface ScanJob {
+  id: string;
+  repo: string;
+  phase: ScanPhase;
+  files_done: number;
+  files_total: number;
+  chunks_done: number;
+  chunks_total: number;
+  errors: string[];
+  started_at: number;
+  finished_at: number | null;
+  report: ScanReport | null;
+}
+
+export interface CostEstimate {
+  provider: string;
+  model: string;
+  usd_per_million_tokens: number | null; // null : modèle absent de la grille de prix
+  cost_usd: number | null;
+  configured: boolean;
+}
+
+export interface ScanEstimate {
+  repo: string;
+  files: number;
+  files_skipped: { path: string; reason: string }[];
+  chunks: number;
+  chunks_deduplicated: number;
+  chunks_per_language: Record<string, number>;
+  chunks_per_type: Record<string, number>;
+  secrets_redacted: Record<string, number>;
+  tokens: number;
+  costs: CostEstimate[];
 }
 
 export interface AskResponse {
@@ -26,15 +98,48 @@ export interface RepoListResponse {
 
 // Service functions
 /**
- * Index a repository on the server
+ * Start a background indexing job on the server
  * @param repoPath - absolute or relative path to the repo
  * @param filterConfig - optional filter configuration for include/exclude paths
+ * @param gitRef - optional branch, tag or commit to index instead of the checked out files
  */
-export function scanRepo(repoPath: string, filterConfig?: FilterConfig): Promise<ScanResponse> {
-  const payload = { repo_path: repoPath, ...(filterConfig && { filter: filterConfig }) };
+export function scanRepo(repoPath: string, filterConfig?: FilterConfig, gitRef?: string): Promise<ScanResponse> {
+  const payload = {
+    repo_path: repoPath,
+    ...(filterConfig && { filter: filterConfig }),
+    ...(gitRef && { git_ref: gitRef }),
+  };
   return api.post<ScanResponse>("/scan_repo", payload).then((res) => res.data);
 }
 
+/**
+ * Dry run of a scan: files, chunks, tokens and embedding cost, nothing is embedded
+ */
+export function estimateScan(repoPath: string, filterConfig?: FilterConfig, gitRef?: string): Promise<ScanEstimate> {
+  const payload = {
+    repo_path: repoPath,
+    ...(filterConfig && { filter: filterConfig }),
+    ...(gitRef && { git_ref: gitRef }),
+  };
+  return api.post<ScanEstimate>("/scan_repo/estimate", payload).then((res) => res.data);
+}
+
+/**
+ * Retrieve the status of a scan job
+ * @param jobId - id returned by scanRepo
+ */
+export function getScanJob(jobId: string): Promise<ScanJob> {
+  return api.get<ScanJob>(`/scan_jobs/${jobId}`).then((res) => res.data);
+}
+
+/**
+ * Cancel a running scan job
+ * @param jobId - id returned by scanRepo
+ */
+export function cancelScanJob(jobId: string): Promise<ScanJob> {
+  return api.post<ScanJob>(`/scan_jobs/${jobId}/cancel`).then((res) => res.data);
+}
+
 /**
  * Ask a question against an indexed repository
  * @param repo_identifier - identifier of the indexed repo
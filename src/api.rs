@@ -1,32 +1,51 @@
 #![cfg(feature = "api")]
 
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 
 use axum::{
-    body::Body, extract::State, http::StatusCode, response::{IntoResponse}, routing::{get, post}, Json, Router
+    body::Body, extract::{Path, State}, http::StatusCode, response::{IntoResponse}, routing::{delete, get, post}, Json, Router
 };
 use futures_util::stream::StreamExt;
 use qdrant_client::{Qdrant, config::QdrantConfig, qdrant::ListCollectionsResponse};
 use serde::{Deserialize, Serialize};
 use tracing::error;
 
-/// ═════════════════════ modèles JSON ═════════════════════
+use crate::jobs::{JobRegistry, JobStatus, ScanPhase, ScanProgress};
 
-#[derive(Deserialize)]
-struct FilterConfig {
-    mode: String, // "include" or "exclude"
-    paths: Vec<String>,
-}
+// ═════════════════════ modèles JSON ═════════════════════
 
 #[derive(Deserialize)]
 struct ScanRequest {
     repo_path: String,
-    filter: Option<FilterConfig>,
+    git_ref: Option<String>, // branch, tag or commit; the checked out files when absent
+    filter: Option<crate::types::FilterConfig>, // ordered include/exclude rules
+    #[serde(default)]
+    mode: crate::types::ScanMode, // "full" (default) or "incremental"
 }
 
 #[derive(Serialize)]
 struct ScanResponse {
     repo_identifier: String,
+    job_id: String,
+}
+
+#[derive(Deserialize)]
+struct CloneRequest {
+    url: String,              // https, ssh, file:// or a local path
+    name: Option<String>,     // clone/<name>; the last segment of the url by default
+    git_ref: Option<String>,  // branch, tag or commit to check out
+    #[serde(default)]
+    scan: bool,               // start a scan job once cloned
+    #[serde(default)]
+    mode: crate::types::ScanMode,
+}
+
+#[derive(Serialize)]
+struct CloneResponse {
+    repo_identifier: String,
+    commit: String,
+    job_id: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -34,6 +53,29 @@ struct AskRequest {
     question: String,
     instructions: String,
     repo_identifier: String,
+    git_ref: Option<String>,
+    #[serde(default)]
+    scope: crate::types::ChunkScope, // "all" (default), "code" or "docs"
+}
+
+#[derive(Deserialize)]
+struct AskWorkspaceRequest {
+    question: String,
+    instructions: String,
+    workspace: String,
+    #[serde(default)]
+    scope: crate::types::ChunkScope,
+}
+
+#[derive(Deserialize)]
+struct WorkspaceRequest {
+    repos: Vec<String>, // indexed repos (collection names, e.g. "backend" or "shared@v2")
+}
+
+#[derive(Deserialize)]
+struct WatchRequest {
+    repo_path: String, // clone/<repo_path>, watched in place
+    filter: Option<crate::types::FilterConfig>, // ordered include/exclude rules
 }
 
 #[derive(Serialize)]
@@ -41,26 +83,208 @@ struct RepoListResponse {
     repos: Vec<String>,
 }
 
-/// ═════════════════════ global state ═════════════════════
+// ═════════════════════ global state ═════════════════════
 #[derive(Clone)]
 struct AppState {
     qdrant: Arc<Qdrant>,
+    jobs: JobRegistry,
+    watches: Arc<Mutex<HashMap<String, tokio::task::JoinHandle<()>>>>, // repo → watch task
 }
 
-/// ═════════════════════ handlers ═════════════════════
+// ═════════════════════ handlers ═════════════════════
 
-/// POST /scan_repo
+/// POST /scan_repo — starts a background scan job and returns its id
 async fn scan_repo_handler(
+    State(state): State<AppState>,
     Json(req): Json<ScanRequest>,
-) -> Result<Json<ScanResponse>, (StatusCode, String)> {
-    let filter_config = req.filter.map(|f| crate::types::FilterConfig {
-        mode: f.mode,
-        paths: f.paths,
+) -> Result<(StatusCode, Json<ScanResponse>), (StatusCode, String)> {
+    let filter_config = req.filter;
+
+    let repo_identifier = crate::git::collection_name(&req.repo_path, req.git_ref.as_deref());
+    let progress = state
+        .jobs
+        .start(&repo_identifier)
+        .map_err(|e| (StatusCode::CONFLICT, e))?;
+    let job_id = progress.id();
+
+    tokio::spawn(run_scan_job(
+        req.repo_path,
+        req.git_ref,
+        filter_config,
+        req.mode,
+        progress,
+    ));
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(ScanResponse {
+            repo_identifier,
+            job_id,
+        }),
+    ))
+}
+
+async fn run_scan_job(
+    repo_name: String,
+    git_ref: Option<String>,
+    filter_config: Option<crate::types::FilterConfig>,
+    mode: crate::types::ScanMode,
+    progress: ScanProgress,
+) {
+    let job = progress.clone();
+    let handle = tokio::spawn(async move {
+        crate::scan_repo(repo_name, git_ref, filter_config, mode, &job).await
     });
-    
-    let repo_identifier = crate::scan_repo(req.repo_path.clone(), filter_config).await;
 
-    Ok(Json(ScanResponse { repo_identifier }))
+    // scan_repo reports its own errors, this only catches an unexpected panic
+    if let Err(e) = handle.await {
+        error!(?e, "Scan job failed");
+        progress.error(format!("Scan failed: {e}"));
+        progress.set_phase(ScanPhase::Failed);
+    }
+}
+
+/// POST /repos — clones (or fetches) a repository into clone/, optionally followed by a scan job
+async fn clone_repo_handler(
+    State(state): State<AppState>,
+    Json(req): Json<CloneRequest>,
+) -> Result<Json<CloneResponse>, (StatusCode, String)> {
+    let url = req.url.clone();
+    let name = req.name.clone();
+    let git_ref = req.git_ref.clone();
+    let (repo_name, commit) = tokio::task::spawn_blocking(move || {
+        crate::clone_repo(&url, name.as_deref(), git_ref.as_deref())
+    })
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .map_err(|e| {
+        error!(%e, "Clone failed");
+        (StatusCode::BAD_REQUEST, e)
+    })?;
+
+    let repo_identifier = crate::git::collection_name(&repo_name, req.git_ref.as_deref());
+    let job_id = if req.scan {
+        let progress = state
+            .jobs
+            .start(&repo_identifier)
+            .map_err(|e| (StatusCode::CONFLICT, e))?;
+        let job_id = progress.id();
+        tokio::spawn(run_scan_job(repo_name, req.git_ref, None, req.mode, progress));
+        Some(job_id)
+    } else {
+        None
+    };
+
+    Ok(Json(CloneResponse {
+        repo_identifier,
+        commit,
+        job_id,
+    }))
+}
+
+/// POST /scan_repo/estimate — dry run: files, chunks, tokens and cost, nothing is embedded
+async fn estimate_scan_handler(
+    Json(req): Json<ScanRequest>,
+) -> Result<Json<crate::ScanEstimate>, (StatusCode, String)> {
+    let filter_config = req.filter;
+
+    tokio::task::spawn_blocking(move || {
+        crate::estimate_scan(req.repo_path, req.git_ref, filter_config)
+    })
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .map(Json)
+    .map_err(|e| (StatusCode::BAD_REQUEST, e))
+}
+
+/// POST /watches — keeps the index of clone/<repo_path> in sync with its files,
+/// every re-index shows up as a scan job
+async fn start_watch_handler(
+    State(state): State<AppState>,
+    Json(req): Json<WatchRequest>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    // Ni `../`, ni chemin absolu, ni `repo/` qui doublerait la surveillance de `repo`
+    if !crate::git::is_valid_repo_name(&req.repo_path) {
+        return Err((StatusCode::BAD_REQUEST, format!("Invalid repo name {}", req.repo_path)));
+    }
+    if !std::path::Path::new("clone").join(&req.repo_path).is_dir() {
+        return Err((StatusCode::NOT_FOUND, format!("No repository clone/{}", req.repo_path)));
+    }
+    let filter_config = req.filter;
+
+    let mut watches = state.watches.lock().unwrap();
+    if watches.get(&req.repo_path).is_some_and(|w| !w.is_finished()) {
+        return Err((StatusCode::CONFLICT, format!("{} is already watched", req.repo_path)));
+    }
+
+    let repo = req.repo_path.clone();
+    let jobs = state.jobs.clone();
+    let handle = tokio::spawn(async move {
+        let watched = crate::watch_repo(repo.clone(), filter_config, || jobs.start(&repo)).await;
+        if let Err(e) = watched {
+            error!(%e, "Watch of {repo} stopped");
+        }
+    });
+    watches.insert(req.repo_path, handle);
+
+    Ok(StatusCode::CREATED)
+}
+
+/// GET /watches
+async fn list_watches_handler(State(state): State<AppState>) -> Json<RepoListResponse> {
+    let watches = state.watches.lock().unwrap();
+    let mut repos: Vec<String> = watches
+        .iter()
+        .filter(|(_, handle)| !handle.is_finished())
+        .map(|(repo, _)| repo.clone())
+        .collect();
+    repos.sort();
+    Json(RepoListResponse { repos })
+}
+
+/// DELETE /watches/{repo}
+async fn stop_watch_handler(
+    State(state): State<AppState>,
+    Path(repo): Path<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let handle = state
+        .watches
+        .lock()
+        .unwrap()
+        .remove(&repo)
+        .ok_or((StatusCode::NOT_FOUND, format!("{repo} is not watched")))?;
+    handle.abort();
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// GET /scan_jobs
+async fn list_scan_jobs_handler(State(state): State<AppState>) -> Json<Vec<JobStatus>> {
+    Json(state.jobs.list())
+}
+
+/// GET /scan_jobs/{id}
+async fn scan_job_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<JobStatus>, (StatusCode, String)> {
+    state
+        .jobs
+        .get(&id)
+        .map(|job| Json(job.snapshot()))
+        .ok_or((StatusCode::NOT_FOUND, format!("No scan job {id}")))
+}
+
+/// POST /scan_jobs/{id}/cancel
+async fn cancel_scan_job_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<JobStatus>, (StatusCode, String)> {
+    let job = state
+        .jobs
+        .get(&id)
+        .ok_or((StatusCode::NOT_FOUND, format!("No scan job {id}")))?;
+    job.cancel();
+    Ok(Json(job.snapshot()))
 }
 
 /// POST /ask_repo
@@ -68,7 +292,21 @@ async fn ask_repo_handler(Json(req): Json<AskRequest>) -> impl IntoResponse {
     let (tx, rx) = tokio::sync::mpsc::channel::<String>(16);
 
     tokio::spawn(async move {
-        let _ = crate::ask_repo(req.question, req.instructions, req.repo_identifier, tx).await;
+        let error_tx = tx.clone();
+        let asked = crate::ask_repo(
+            req.question,
+            req.instructions,
+            req.repo_identifier,
+            req.git_ref,
+            req.scope,
+            tx,
+        )
+        .await;
+        // Sans ça, un index incompatible donnerait une réponse vide
+        if let Err(e) = asked {
+            error!(%e, "Question failed");
+            let _ = error_tx.send(e).await;
+        }
     });
 
     let stream = tokio_stream::wrappers::ReceiverStream::new(rx)
@@ -80,13 +318,62 @@ async fn ask_repo_handler(Json(req): Json<AskRequest>) -> impl IntoResponse {
     )
 }
 
+/// POST /ask_workspace — same as /ask_repo, across every repo of a workspace
+async fn ask_workspace_handler(Json(req): Json<AskWorkspaceRequest>) -> impl IntoResponse {
+    let (tx, rx) = tokio::sync::mpsc::channel::<String>(16);
+
+    tokio::spawn(async move {
+        let error_tx = tx.clone();
+        if let Err(e) =
+            crate::ask_workspace(req.question, req.instructions, req.workspace, req.scope, tx).await
+        {
+            error!(%e, "Workspace question failed");
+            let _ = error_tx.send(e).await;
+        }
+    });
+
+    let stream = tokio_stream::wrappers::ReceiverStream::new(rx)
+        .map(|chunk| Ok::<_, std::io::Error>(chunk.into_bytes()));
+
+    (
+        [("Content-Type", "text/plain; charset=utf-8")],
+        Body::from_stream(stream),
+    )
+}
+
+/// GET /workspaces
+async fn list_workspaces_handler() -> Result<Json<Vec<crate::Workspace>>, (StatusCode, String)> {
+    crate::list_workspaces()
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
+}
+
+/// PUT /workspaces/{name} — creates the workspace or replaces its repos
+async fn save_workspace_handler(
+    Path(name): Path<String>,
+    Json(req): Json<WorkspaceRequest>,
+) -> Result<Json<crate::Workspace>, (StatusCode, String)> {
+    crate::save_workspace(&name, req.repos)
+        .map(Json)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))
+}
+
+/// DELETE /workspaces/{name}
+async fn delete_workspace_handler(Path(name): Path<String>) -> Result<StatusCode, (StatusCode, String)> {
+    match crate::delete_workspace(&name) {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err((StatusCode::NOT_FOUND, format!("No workspace {name}"))),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e)),
+    }
+}
+
 /// GET /repos
 async fn list_repos_handler(
     State(state): State<AppState>,
 ) -> Result<Json<RepoListResponse>, (StatusCode, String)> {
     let resp: ListCollectionsResponse = state.qdrant.list_collections().await.map_err(|e| {
         error!(?e, "Error listing Qdrant collections");
-        return (StatusCode::BAD_GATEWAY, e.to_string());
+        (StatusCode::BAD_GATEWAY, e.to_string())
     })?;
 
     let repos = resp
@@ -109,7 +396,7 @@ async fn list_indexable_repos() -> Result<Json<RepoListResponse>, (StatusCode, S
     }
 }
 
-/// ═════════════════════ public router ═════════════════════
+// ═════════════════════ public router ═════════════════════
 
 pub fn build_router() -> Router {
     let config = QdrantConfig::from_url("http://qdrant:6334").skip_compatibility_check();
@@ -126,12 +413,26 @@ pub fn build_router() -> Router {
 
     let state = AppState {
         qdrant: Arc::new(client),
+        jobs: JobRegistry::default(),
+        watches: Arc::default(),
     };
 
     Router::new()
         .route("/scan_repo", post(scan_repo_handler))
+        .route("/scan_repo/estimate", post(estimate_scan_handler))
+        .route("/scan_jobs", get(list_scan_jobs_handler))
+        .route("/scan_jobs/{id}", get(scan_job_handler))
+        .route("/scan_jobs/{id}/cancel", post(cancel_scan_job_handler))
+        .route("/watches", get(list_watches_handler).post(start_watch_handler))
+        .route("/watches/{repo}", delete(stop_watch_handler))
         .route("/ask_repo", post(ask_repo_handler))
-        .route("/repos", get(list_repos_handler))
+        .route("/ask_workspace", post(ask_workspace_handler))
+        .route("/workspaces", get(list_workspaces_handler))
+        .route(
+            "/workspaces/{name}",
+            axum::routing::put(save_workspace_handler).delete(delete_workspace_handler),
+        )
+        .route("/repos", get(list_repos_handler).post(clone_repo_handler))
         .route("/indexable-repos", get(list_indexable_repos))
         .with_state(state)
 }
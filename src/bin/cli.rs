@@ -1,6 +1,6 @@
 #![cfg(feature = "cli")]
 
-use clap::{Parser, Subcommand, arg, command};
+use clap::{Parser, Subcommand};
 
 #[derive(Parser)]
 #[command(name = "better-deepwiki")]
@@ -12,17 +12,75 @@ struct Cli {
 
 #[derive(Subcommand)]
 enum Commands {
+    /// Clone a repository into clone/, or fetch it if already cloned
+    Clone {
+        /// https, ssh, file:// url or local path of the repository
+        url: String,
+        /// Directory name under clone/; the last segment of the url by default
+        #[arg(long)]
+        name: Option<String>,
+        /// Branch, tag or commit to check out
+        #[arg(long)]
+        git_ref: Option<String>,
+        /// Index the repository once cloned
+        #[arg(long)]
+        scan: bool,
+    },
     Embed {
         #[arg(long)]
         repo_path: String,
+        /// Branch, tag or commit to index instead of the checked out files
+        #[arg(long)]
+        git_ref: Option<String>,
+        /// Only re-embed files added or changed since the last scan
+        #[arg(long)]
+        incremental: bool,
+        /// Only print the files, chunks, tokens and cost the scan would embed
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Re-index the files of clone/<repo_path> as they change, until interrupted
+    Watch {
+        #[arg(long)]
+        repo_path: String,
+    },
+    /// Manage the workspaces, named groups of indexed repos questioned together
+    Workspace {
+        #[command(subcommand)]
+        command: WorkspaceCommands,
     },
     Query {
         #[arg(long)]
         question: String,
         #[arg(long)]
         instructions: String,
+        #[arg(long, required_unless_present = "workspace")]
+        repo_path: Option<String>,
+        /// Query the index of this branch, tag or commit
         #[arg(long)]
-        repo_path: String,
+        git_ref: Option<String>,
+        /// Query every repo of this workspace instead of a single repo
+        #[arg(long, conflicts_with_all = ["repo_path", "git_ref"])]
+        workspace: Option<String>,
+        /// Answer from "all" chunks, only "code" or only "docs"
+        #[arg(long, default_value = "all")]
+        scope: better_deep_wiki::ChunkScope,
+    },
+}
+
+#[derive(Subcommand)]
+enum WorkspaceCommands {
+    /// List the workspaces and their repos
+    List,
+    /// Create a workspace or replace its repos
+    Set {
+        name: String,
+        /// Indexed repos (collection names, e.g. backend or shared@v2), comma separated
+        #[arg(long, value_delimiter = ',', required = true)]
+        repos: Vec<String>,
+    },
+    Remove {
+        name: String,
     },
 }
 
@@ -34,19 +92,115 @@ async fn main() {
     let cli = Cli::parse();
 
     match cli.command {
-        Commands::Embed { repo_path } => {
-            better_deep_wiki::scan_repo(repo_path, None).await;
+        Commands::Clone {
+            url,
+            name,
+            git_ref,
+            scan,
+        } => {
+            let (repo_name, commit) =
+                match better_deep_wiki::clone_repo(&url, name.as_deref(), git_ref.as_deref()) {
+                    Ok(cloned) => cloned,
+                    Err(err) => {
+                        eprintln!("❌ {err}");
+                        return;
+                    }
+                };
+            println!("✅ clone/{repo_name} at {commit}");
+
+            if scan {
+                let progress = better_deep_wiki::ScanProgress::new(&repo_name);
+                let mode = better_deep_wiki::ScanMode::Full;
+                match better_deep_wiki::scan_repo(repo_name, git_ref, None, mode, &progress).await {
+                    Ok(report) => println!(
+                        "{}",
+                        serde_json::to_string_pretty(&report).unwrap_or_default()
+                    ),
+                    Err(err) => eprintln!("❌ {err}"),
+                }
+            }
+        }
+
+        Commands::Embed {
+            repo_path,
+            git_ref,
+            incremental,
+            dry_run,
+        } => {
+            if dry_run {
+                match better_deep_wiki::estimate_scan(repo_path, git_ref, None) {
+                    Ok(estimate) => println!(
+                        "{}",
+                        serde_json::to_string_pretty(&estimate).unwrap_or_default()
+                    ),
+                    Err(err) => eprintln!("❌ {err}"),
+                }
+                return;
+            }
+            let mode = if incremental {
+                better_deep_wiki::ScanMode::Incremental
+            } else {
+                better_deep_wiki::ScanMode::Full
+            };
+            let progress = better_deep_wiki::ScanProgress::new(&repo_path);
+            match better_deep_wiki::scan_repo(repo_path, git_ref, None, mode, &progress).await {
+                Ok(report) => println!(
+                    "{}",
+                    serde_json::to_string_pretty(&report).unwrap_or_default()
+                ),
+                Err(err) => eprintln!("❌ {err}"),
+            }
+        }
+
+        Commands::Watch { repo_path } => {
+            let start = || Ok(better_deep_wiki::ScanProgress::new(&repo_path));
+            if let Err(err) = better_deep_wiki::watch_repo(repo_path.clone(), None, start).await {
+                eprintln!("❌ {err}");
+            }
+        }
+
+        Commands::Workspace { command } => {
+            let result = match command {
+                WorkspaceCommands::List => better_deep_wiki::list_workspaces().map(|workspaces| {
+                    for workspace in workspaces {
+                        println!("{}: {}", workspace.name, workspace.repos.join(", "));
+                    }
+                }),
+                WorkspaceCommands::Set { name, repos } => better_deep_wiki::save_workspace(&name, repos)
+                    .map(|workspace| println!("✅ {}: {}", workspace.name, workspace.repos.join(", "))),
+                WorkspaceCommands::Remove { name } => match better_deep_wiki::delete_workspace(&name) {
+                    Ok(true) => {
+                        println!("✅ {name} removed");
+                        Ok(())
+                    }
+                    Ok(false) => Err(format!("No workspace {name}")),
+                    Err(e) => Err(e),
+                },
+            };
+            if let Err(err) = result {
+                eprintln!("❌ {err}");
+            }
         }
 
         Commands::Query {
             question,
             instructions,
             repo_path,
+            git_ref,
+            workspace,
+            scope,
         } => {
             let (tx, mut rx) = tokio::sync::mpsc::channel::<String>(16);
             
             tokio::spawn(async move {
-                if let Err(err) = better_deep_wiki::ask_repo(question, instructions, repo_path, tx).await {
+                let asked = match workspace {
+                    Some(workspace) => better_deep_wiki::ask_workspace(question, instructions, workspace, scope, tx).await,
+                    None => {
+                        let repo_path = repo_path.unwrap_or_default();
+                        better_deep_wiki::ask_repo(question, instructions, repo_path, git_ref, scope, tx).await
+                    }
+                };
+                if let Err(err) = asked {
                     eprintln!("❌ {err}");
                 }
             });
@@ -56,8 +56,7 @@ pub async fn chat_mistral_stream(prompt: String, tx: Sender<String>) -> Result<(
         let chunk_str = String::from_utf8_lossy(&chunk);
 
         for line in chunk_str.lines() {
-            if line.starts_with("data: ") {
-                let data = &line[6..];
+            if let Some(data) = line.strip_prefix("data: ") {
                 if let Ok(value) = serde_json::from_str::<serde_json::Value>(data) {
                     if let Some(content) = value["choices"]
                         .get(0)
@@ -112,8 +111,7 @@ pub async fn chat_openai_stream(prompt: String, tx: Sender<String>) -> Result<()
         let chunk_str = String::from_utf8_lossy(&chunk);
 
         for line in chunk_str.lines() {
-            if line.starts_with("data: ") {
-                let data = &line[6..];
+            if let Some(data) = line.strip_prefix("data: ") {
                 if data.trim() == "[DONE]" {
                     break;
                 }
@@ -0,0 +1,153 @@
+use crate::parsing::FileMeta;
+use crate::repo_config::ScanSettings;
+use crate::types::ScanMode;
+use crate::utils;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs;
+
+/// Progress of the embedding of a `generated/<repo>` chunk file.
+/// Batches are rebuilt from the chunk file in the same order on every run,
+/// so their index is enough to know what is already stored in Qdrant.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Checkpoint {
+    pub fingerprint: String,
+    pub total_batches: usize,
+    pub committed_batches: BTreeSet<usize>,
+}
+
+impl Checkpoint {
+    pub fn new(fingerprint: String, total_batches: usize) -> Self {
+        Self {
+            fingerprint,
+            total_batches,
+            committed_batches: BTreeSet::new(),
+        }
+    }
+
+    pub fn path(repo_name: &str) -> String {
+        format!("generated/{repo_name}.checkpoint.json")
+    }
+
+    pub fn load(path: &str) -> Option<Self> {
+        let content = fs::read_to_string(path).ok()?;
+        match serde_json::from_str(&content) {
+            Ok(checkpoint) => Some(checkpoint),
+            Err(e) => {
+                eprintln!("Warning: ignoring invalid checkpoint {path}: {e}");
+                None
+            }
+        }
+    }
+
+    /// Write to a temporary file then rename, so a crash never leaves a truncated checkpoint.
+    pub fn save(&self, path: &str) -> std::io::Result<()> {
+        let tmp = format!("{path}.tmp");
+        fs::write(&tmp, serde_json::to_vec(self)?)?;
+        fs::rename(tmp, path)
+    }
+
+    pub fn remove(path: &str) {
+        let _ = fs::remove_file(path);
+    }
+
+    pub fn is_committed(&self, batch: usize) -> bool {
+        self.committed_batches.contains(&batch)
+    }
+
+    pub fn commit(&mut self, batch: usize) {
+        self.committed_batches.insert(batch);
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.committed_batches.len() == self.total_batches
+    }
+}
+
+/// Version of the encoding of `Chunk` in the chunk files, bumped when its fields change
+/// so that a checkpoint never resumes on a chunk file it can't read.
+const CHUNK_FORMAT: u32 = 9;
+
+/// Identify a scan: the same files with the same content, mode, chunking and redaction
+/// settings always produce the same chunk file.
+pub fn fingerprint(meta_files: &[FileMeta], mode: ScanMode, settings: &ScanSettings) -> String {
+    // chunk_as : le langage donné par un shebang ou un mapping du `.deepwiki.toml`
+    let mut files: Vec<_> = meta_files
+        .iter()
+        .map(|m| format!("{}:{}:{}", m.path, m.hash, m.chunk_as.unwrap_or_default()))
+        .collect();
+    files.sort();
+
+    // Reprendre un scan garantit ainsi que ses vecteurs viennent du même modèle
+    let (provider, model) = utils::configured_embedding_model();
+    let config = crate::config::Config::from_env();
+    // Et qu'un fichier de chunks non masqué n'est pas envoyé quand le masquage est activé
+    let redaction = format!(
+        "{}/{}/{}",
+        config.redact_secrets,
+        config.redaction_min_entropy,
+        settings.redaction_allowlist.join("\u{1f}")
+    );
+    let input = format!(
+        "{CHUNK_FORMAT}\n{mode:?}\n{}\n{}\n{provider}/{model}/{}\n{redaction}\n{}",
+        settings.chunk_size,
+        settings.chunk_overlap,
+        config.vector_dimension,
+        files.join("\n")
+    );
+    utils::hash_content(input.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checkpoint_roundtrip() {
+        let path = std::env::temp_dir().join(format!("bdw-checkpoint-{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        let mut checkpoint = Checkpoint::new("abc".to_string(), 3);
+        checkpoint.commit(0);
+        checkpoint.commit(2);
+        checkpoint.save(path).unwrap();
+
+        let mut loaded = Checkpoint::load(path).unwrap();
+        Checkpoint::remove(path);
+        assert_eq!(loaded.fingerprint, "abc");
+        assert!(loaded.is_committed(0));
+        assert!(!loaded.is_committed(1));
+        assert!(!loaded.is_complete());
+        loaded.commit(1);
+        assert!(loaded.is_complete());
+    }
+
+    #[test]
+    fn test_fingerprint_covers_files_and_settings() {
+        let meta = |path: &str, hash: &str| FileMeta {
+            path: path.to_string(),
+            hash: hash.to_string(),
+            content: None,
+            chunk_as: None,
+        };
+        let settings = ScanSettings::from_env();
+        let a = fingerprint(&[meta("a", "1"), meta("b", "2")], ScanMode::Full, &settings);
+        let b = fingerprint(&[meta("b", "2"), meta("a", "1")], ScanMode::Full, &settings);
+        let c = fingerprint(&[meta("b", "3"), meta("a", "1")], ScanMode::Full, &settings);
+        let d = fingerprint(&[meta("a", "1"), meta("b", "2")], ScanMode::Incremental, &settings);
+        let mapped = FileMeta {
+            chunk_as: Some("js"),
+            ..meta("b", "2")
+        };
+        let e = fingerprint(&[meta("a", "1"), mapped], ScanMode::Full, &settings);
+        let allowlisted = ScanSettings {
+            redaction_allowlist: vec!["^pk_test_".to_string()],
+            ..settings.clone()
+        };
+        let f = fingerprint(&[meta("a", "1"), meta("b", "2")], ScanMode::Full, &allowlisted);
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert_ne!(a, d);
+        assert_ne!(a, e);
+        assert_ne!(a, f);
+    }
+}
@@ -82,8 +82,8 @@ mod tests {
             chunk_end_line: 5,
             chunk_start_line: 0,
             text: "yo".into(),
-            function_name: None,
             chunk_type: crate::types::ChunkType::Class,
+            ..Default::default()
         };
         writer.write(&chunk).unwrap();
         writer.flush().unwrap();
@@ -1,5 +1,4 @@
 use crate::types::{Chunk, ChunkType};
-use std::fs;
 use std::path::Path;
 use tiktoken_rs::{cl100k_base, CoreBPE};
 
@@ -9,36 +8,42 @@ pub struct TextSplitter {
 }
 
 impl TextSplitter {
-    pub fn split_file(&self, path: &Path) -> Result<Vec<Chunk>, String> {
-        let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
-
-        let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or("");
-
+    /// Split the loaded content of a file. `extension` picks the chunker, it is the one of
+    /// `path` unless given by a shebang or a mapping.
+    pub fn split_content(
+        &self,
+        path: &Path,
+        extension: &str,
+        content: &str,
+    ) -> Result<Vec<Chunk>, String> {
         let enc = cl100k_base().map_err(|e| e.to_string())?;
 
+        if crate::doc_chunking::is_doc(extension) {
+            return crate::doc_chunking::chunk_doc(content, path, self, &enc);
+        }
+
         let chunks = match extension {
-            "rs" => crate::intelligent_chunking::chunk_rust(&content, path, self.chunk_size, &enc)?,
-            "js" | "jsx" => crate::intelligent_chunking::chunk_javascript(
-                &content,
-                path,
-                self.chunk_size,
-                &enc,
-            )?,
-            "ts" | "tsx" => {
-                crate::intelligent_chunking::chunk_tsx(&content, path, self.chunk_size, &enc)?
-            }
-            "java" => {
-                crate::intelligent_chunking::chunk_java(&content, path, self.chunk_size, &enc)?
-            }
+            "rs" => crate::intelligent_chunking::chunk_rust(content, path, self, &enc)?,
+            "js" | "jsx" => crate::intelligent_chunking::chunk_javascript(content, path, self, &enc)?,
+            "ts" | "tsx" => crate::intelligent_chunking::chunk_tsx(content, path, self, &enc)?,
+            "c" => crate::intelligent_chunking::chunk_c(content, path, self, &enc)?,
+            // Les en-têtes .h peuvent être du C++ : la grammaire C++ lit aussi le C
+            "cpp" | "cc" | "cxx" | "h" | "hh" | "hpp" => crate::intelligent_chunking::chunk_cpp(content, path, self, &enc)?,
+            "cs" => crate::intelligent_chunking::chunk_csharp(content, path, self, &enc)?,
+            "go" => crate::intelligent_chunking::chunk_go(content, path, self, &enc)?,
+            "java" => crate::intelligent_chunking::chunk_java(content, path, self, &enc)?,
+            "php" => crate::intelligent_chunking::chunk_php(content, path, self, &enc)?,
+            "py" => crate::intelligent_chunking::chunk_python(content, path, self, &enc)?,
+            "rb" => crate::intelligent_chunking::chunk_ruby(content, path, self, &enc)?,
             _ => {
                 // Fallback to line chunking for unsupported extensions
-                return self.split_text(&content, path.to_string_lossy().to_string(), &enc);
+                return self.split_text(content, path.to_string_lossy().to_string(), &enc);
             }
         };
 
         if chunks.is_empty() {
             // Fallback if tree-sitter parsing failed
-            self.split_text(&content, path.to_string_lossy().to_string(), &enc)
+            self.split_text(content, path.to_string_lossy().to_string(), &enc)
         } else {
             Ok(chunks)
         }
@@ -81,8 +86,8 @@ impl TextSplitter {
                 chunk_start_line: start_index,
                 path: path.clone(),
                 text: chunk_text,
-                function_name: None,
                 chunk_type: ChunkType::LineChunk,
+                ..Default::default()
             });
 
             chunk_index += 1;
@@ -6,6 +6,15 @@ pub struct Config {
     pub chunk_overlap: usize,
     pub vector_dimension: usize,
     pub top_k: u64,
+    pub embedding_concurrency: usize, // batches sent to the embedding API at the same time
+    pub embedding_max_retries: u32,   // per batch, on network errors, 408, 429 and 5xx
+    pub watch_debounce_ms: u64,       // quiet time before re-indexing files changed in watch mode
+    pub max_file_size: u64,           // bytes, larger files are skipped (0 = no limit)
+    pub max_line_length: usize,       // characters, code with a longer line is deemed minified (0 = no limit)
+    pub max_average_line_length: usize, // same, for the average line length
+    pub skip_generated: bool,         // skip files with an `@generated` or `DO NOT EDIT` header
+    pub redact_secrets: bool,         // replace secrets with placeholders before chunks leave the machine
+    pub redaction_min_entropy: f64,   // bits per character from which a token is deemed a secret
 }
 
 impl Default for Config {
@@ -15,6 +24,15 @@ impl Default for Config {
             chunk_overlap: 100,
             vector_dimension: 1536,
             top_k: 10,
+            embedding_concurrency: 4,
+            embedding_max_retries: 5,
+            watch_debounce_ms: 2000,
+            max_file_size: 1_000_000,
+            max_line_length: 2000,
+            max_average_line_length: 300,
+            skip_generated: true,
+            redact_secrets: true,
+            redaction_min_entropy: 4.0,
         }
     }
 }
@@ -43,6 +61,52 @@ impl Config {
                 .ok()
                 .and_then(|s| s.parse().ok())
                 .unwrap_or(default.top_k),
+
+            embedding_concurrency: env::var("EMBEDDING_CONCURRENCY")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .filter(|n| *n > 0)
+                .unwrap_or(default.embedding_concurrency),
+
+            embedding_max_retries: env::var("EMBEDDING_MAX_RETRIES")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(default.embedding_max_retries),
+
+            watch_debounce_ms: env::var("WATCH_DEBOUNCE_MS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(default.watch_debounce_ms),
+
+            max_file_size: env::var("MAX_FILE_SIZE")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(default.max_file_size),
+
+            max_line_length: env::var("MAX_LINE_LENGTH")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(default.max_line_length),
+
+            max_average_line_length: env::var("MAX_AVERAGE_LINE_LENGTH")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(default.max_average_line_length),
+
+            skip_generated: env::var("SKIP_GENERATED_FILES")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(default.skip_generated),
+
+            redact_secrets: env::var("REDACT_SECRETS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(default.redact_secrets),
+
+            redaction_min_entropy: env::var("REDACTION_MIN_ENTROPY")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(default.redaction_min_entropy),
         }
     }
 }
\ No newline at end of file
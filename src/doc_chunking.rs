@@ -0,0 +1,346 @@
+use crate::chunking::TextSplitter;
+use crate::types::{Chunk, ChunkType};
+use std::path::Path;
+use tiktoken_rs::CoreBPE;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DocFormat {
+    Markdown,
+    Rst,
+    AsciiDoc,
+    Org,
+    Plain,
+}
+
+impl DocFormat {
+    fn from_extension(extension: &str) -> Option<Self> {
+        match extension {
+            "md" | "markdown" => Some(Self::Markdown),
+            "rst" => Some(Self::Rst),
+            "adoc" | "asciidoc" => Some(Self::AsciiDoc),
+            "org" => Some(Self::Org),
+            "txt" => Some(Self::Plain),
+            _ => None,
+        }
+    }
+}
+
+pub fn is_doc(extension: &str) -> bool {
+    DocFormat::from_extension(extension).is_some()
+}
+
+#[derive(Debug)]
+struct Heading {
+    line: usize, // first line of the heading (the overline for RST)
+    body: usize, // first line after the heading
+    level: usize,
+    title: String,
+}
+
+/// Split a documentation file on its heading structure: one chunk per section,
+/// carrying the path of the headings above it (e.g. ["Install", "Docker"]).
+/// Sections larger than `chunk_size` are split by lines, keeping their heading path.
+pub fn chunk_doc(
+    content: &str,
+    file_path: &Path,
+    splitter: &TextSplitter,
+    enc: &CoreBPE,
+) -> Result<Vec<Chunk>, String> {
+    let extension = file_path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    let format = DocFormat::from_extension(extension).unwrap_or(DocFormat::Plain);
+    let lines: Vec<&str> = content.lines().collect();
+
+    let headings = match format {
+        DocFormat::Markdown => markdown_headings(&lines),
+        DocFormat::Rst => rst_headings(&lines),
+        DocFormat::AsciiDoc => asciidoc_headings(&lines),
+        DocFormat::Org => org_headings(&lines),
+        DocFormat::Plain => Vec::new(),
+    };
+
+    // (first line, first body line, heading path) of every section; the preamble has no heading
+    let mut sections: Vec<(usize, usize, Vec<String>)> = vec![(0, 0, Vec::new())];
+    let mut stack: Vec<(usize, String)> = Vec::new();
+    for heading in headings {
+        while stack.last().is_some_and(|(level, _)| *level >= heading.level) {
+            stack.pop();
+        }
+        stack.push((heading.level, heading.title));
+        let heading_path = stack.iter().map(|(_, title)| title.clone()).collect();
+        sections.push((heading.line, heading.body, heading_path));
+    }
+
+    let path = file_path.to_string_lossy().to_string();
+    let mut chunks = Vec::new();
+    let mut section_index = 0;
+
+    for (i, (start, body, heading_path)) in sections.iter().enumerate() {
+        let end = sections.get(i + 1).map_or(lines.len(), |next| next.0);
+        // Un titre sans contenu (suivi directement d'un sous-titre) n'apporte rien seul
+        if lines[(*body).min(end)..end].iter().all(|l| l.trim().is_empty()) {
+            continue;
+        }
+
+        let text = lines[*start..end].join("\n");
+        if enc.encode_ordinary(&text).len() <= splitter.chunk_size {
+            chunks.push(Chunk {
+                path: path.clone(),
+                chunk_index: section_index.to_string(),
+                chunk_start_line: start + 1,
+                chunk_end_line: end,
+                text,
+                chunk_type: ChunkType::Doc,
+                heading_path: heading_path.clone(),
+                ..Default::default()
+            });
+        } else {
+            let mut sub_chunks = splitter.split_text(&text, path.clone(), enc)?;
+            for (sub_index, sub_chunk) in sub_chunks.iter_mut().enumerate() {
+                sub_chunk.chunk_index = format!("{section_index}-{sub_index}");
+                sub_chunk.chunk_type = ChunkType::Doc;
+                sub_chunk.heading_path = heading_path.clone();
+                sub_chunk.chunk_start_line += start + 1;
+                sub_chunk.chunk_end_line += start;
+            }
+            chunks.extend(sub_chunks);
+        }
+        section_index += 1;
+    }
+
+    Ok(chunks)
+}
+
+/// ATX (`## Title`) and setext (`Title` underlined with `===` or `---`) headings,
+/// ignoring fenced code blocks and YAML front matter.
+fn markdown_headings(lines: &[&str]) -> Vec<Heading> {
+    let mut headings: Vec<Heading> = Vec::new();
+    let mut fence: Option<&str> = None;
+    let mut start = 0;
+
+    if lines.first().is_some_and(|l| l.trim_end() == "---") {
+        if let Some(close) = lines.iter().skip(1).position(|l| l.trim_end() == "---") {
+            start = close + 2;
+        }
+    }
+
+    for i in start..lines.len() {
+        let line = lines[i];
+        let trimmed = line.trim_start();
+        if let Some(marker) = fence {
+            if trimmed.starts_with(marker) {
+                fence = None;
+            }
+            continue;
+        }
+        if let Some(marker) = ["```", "~~~"].into_iter().find(|m| trimmed.starts_with(m)) {
+            fence = Some(marker);
+            continue;
+        }
+
+        if let Some((level, title)) = atx_heading(line) {
+            headings.push(Heading {
+                line: i,
+                body: i + 1,
+                level,
+                title,
+            });
+            continue;
+        }
+
+        let underline = line.trim();
+        let previous = if i > start { lines[i - 1].trim() } else { "" };
+        let previous_is_heading = headings.last().is_some_and(|h| h.body >= i);
+        let previous_is_fence = previous.starts_with("```") || previous.starts_with("~~~");
+        if previous.is_empty() || previous_is_heading || previous_is_fence || underline.is_empty() {
+            continue;
+        }
+        let level = if underline.chars().all(|c| c == '=') {
+            1
+        } else if underline.len() >= 2 && underline.chars().all(|c| c == '-') {
+            2
+        } else {
+            continue;
+        };
+        headings.push(Heading {
+            line: i - 1,
+            body: i + 1,
+            level,
+            title: previous.to_string(),
+        });
+    }
+
+    headings
+}
+
+fn atx_heading(line: &str) -> Option<(usize, String)> {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+    let level = trimmed.chars().take_while(|c| *c == '#').count();
+    if level == 0 || level > 6 {
+        return None;
+    }
+    let rest = &trimmed[level..];
+    if !rest.is_empty() && !rest.starts_with([' ', '\t']) {
+        return None;
+    }
+    let title = rest.trim().trim_end_matches('#').trim();
+    Some((level, title.to_string()))
+}
+
+/// Titles underlined (and optionally overlined) with a punctuation character.
+/// As in reStructuredText, levels follow the order in which adornment styles first appear.
+fn rst_headings(lines: &[&str]) -> Vec<Heading> {
+    let mut headings = Vec::new();
+    let mut styles: Vec<(char, bool)> = Vec::new();
+    let mut i = 0;
+
+    while i + 1 < lines.len() {
+        let title = lines[i].trim();
+        let underline = lines[i + 1].trim_end();
+        if title.is_empty()
+            || is_rst_adornment(title)
+            || !is_rst_adornment(underline)
+            || underline.chars().count() < title.chars().count()
+        {
+            i += 1;
+            continue;
+        }
+
+        let overline = i > 0 && lines[i - 1].trim_end() == underline;
+        let style = (underline.chars().next().unwrap_or('='), overline);
+        let level = match styles.iter().position(|s| *s == style) {
+            Some(position) => position + 1,
+            None => {
+                styles.push(style);
+                styles.len()
+            }
+        };
+        headings.push(Heading {
+            line: if overline { i - 1 } else { i },
+            body: i + 2,
+            level,
+            title: title.to_string(),
+        });
+        i += 2;
+    }
+
+    headings
+}
+
+fn is_rst_adornment(line: &str) -> bool {
+    const ADORNMENTS: &str = "=-`:.'\"~^_*+#";
+    let mut chars = line.chars();
+    match chars.next() {
+        Some(first) if ADORNMENTS.contains(first) => {
+            line.len() >= 2 && chars.all(|c| c == first)
+        }
+        _ => false,
+    }
+}
+
+/// `= Title`, `== Section` ..., ignoring delimited blocks (`----`, `....`, `////` ...).
+fn asciidoc_headings(lines: &[&str]) -> Vec<Heading> {
+    let mut headings = Vec::new();
+    let mut block: Option<&str> = None;
+
+    for (i, line) in lines.iter().enumerate() {
+        let trimmed = line.trim_end();
+        if let Some(delimiter) = block {
+            if trimmed == delimiter {
+                block = None;
+            }
+            continue;
+        }
+        let is_delimiter = trimmed.len() >= 4
+            && ['-', '.', '/', '+', '_', '*']
+                .iter()
+                .any(|d| trimmed.chars().all(|c| c == *d));
+        if is_delimiter || trimmed.starts_with("```") {
+            block = Some(trimmed);
+            continue;
+        }
+
+        let level = trimmed.chars().take_while(|c| *c == '=').count();
+        if (1..=6).contains(&level) && trimmed[level..].starts_with(' ') {
+            headings.push(Heading {
+                line: i,
+                body: i + 1,
+                level,
+                title: trimmed[level..].trim().to_string(),
+            });
+        }
+    }
+
+    headings
+}
+
+/// `* Title`, `** Section` ..., ignoring `#+BEGIN_...` blocks.
+fn org_headings(lines: &[&str]) -> Vec<Heading> {
+    let mut headings = Vec::new();
+    let mut in_block = false;
+
+    for (i, line) in lines.iter().enumerate() {
+        let lower = line.trim_start().to_ascii_lowercase();
+        if lower.starts_with("#+begin_") {
+            in_block = true;
+        } else if lower.starts_with("#+end_") {
+            in_block = false;
+        }
+        if in_block {
+            continue;
+        }
+
+        let level = line.chars().take_while(|c| *c == '*').count();
+        if level > 0 && line[level..].starts_with(' ') {
+            headings.push(Heading {
+                line: i,
+                body: i + 1,
+                level,
+                title: line[level..].trim().to_string(),
+            });
+        }
+    }
+
+    headings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tiktoken_rs::cl100k_base;
+
+    fn chunk(content: &str, file: &str) -> Vec<Chunk> {
+        let splitter = TextSplitter {
+            chunk_size: 350,
+            chunk_overlap: 100,
+        };
+        chunk_doc(content, Path::new(file), &splitter, &cl100k_base().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_markdown_heading_path() {
+        let content = "# Guide\n\nIntro.\n\n## Install\n\n```sh\n# not a heading\n```\n\n### Docker\n\nRun it.\n\nUsage\n-----\n\nCall it.\n";
+        let chunks = chunk(content, "README.md");
+        let paths: Vec<_> = chunks.iter().map(|c| c.heading_path.join(" > ")).collect();
+        assert_eq!(paths, vec!["Guide", "Guide > Install", "Guide > Install > Docker", "Guide > Usage"]);
+        assert!(chunks[1].text.contains("# not a heading"));
+        assert_eq!((chunks[2].chunk_start_line, chunks[2].chunk_end_line), (11, 14));
+        assert!(chunks.iter().all(|c| matches!(c.chunk_type, ChunkType::Doc)));
+    }
+
+    #[test]
+    fn test_rst_and_asciidoc_heading_levels() {
+        let rst = "=====\nTitle\n=====\n\nIntro.\n\nSection\n-------\n\nBody.\n\nOther\n=====\n\nMore.\n";
+        let paths: Vec<_> = chunk(rst, "index.rst").into_iter().map(|c| c.heading_path).collect();
+        assert_eq!(
+            paths,
+            vec![vec!["Title"], vec!["Title", "Section"], vec!["Title", "Section", "Other"]]
+        );
+
+        let adoc = "= Manual\n\nIntro.\n\n== Setup\n\n----\n= not a title\n----\n";
+        let paths: Vec<_> = chunk(adoc, "manual.adoc").into_iter().map(|c| c.heading_path).collect();
+        assert_eq!(paths, vec![vec!["Manual"], vec!["Manual", "Setup"]]);
+    }
+}
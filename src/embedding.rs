@@ -1,38 +1,23 @@
-use reqwest::Client;
+use reqwest::{Client, Response};
 use reqwest::header::{AUTHORIZATION, CONTENT_TYPE};
 use serde::{Deserialize, Serialize};
-use std::fmt;
+use std::sync::Arc;
+use tiktoken_rs::cl100k_base_singleton;
 
+use crate::config;
+use crate::rate_limit::{self, RateLimiter, RetryPolicy};
 use crate::types::{Chunk, Embedding};
-use crate::utils::MAX_SEQUENCE_LENGTH;
+use crate::utils;
 
-pub type EmbeddingVector = Vec<f32>;
-
-#[derive(Debug)]
-pub struct EmbedResult {
-    pub input: Vec<String>,
-    pub embeddings: Option<Vec<EmbeddingVector>>,
-    pub error: Option<String>,
-}
-
-impl fmt::Display for EmbedResult {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        if let Some(err) = &self.error {
-            write!(f, "EmbedResult: ERREUR: {err}")
-        } else {
-            write!(
-                f,
-                "EmbedResult: {} textes, {} embeddings",
-                self.input.len(),
-                self.embeddings.as_ref().map(|e| e.len()).unwrap_or(0)
-            )
-        }
-    }
+pub struct EmbeddedBatch {
+    pub embeddings: Vec<Embedding>,
+    pub skipped: Vec<Chunk>, // empty or longer than MAX_SEQUENCE_LENGTH, never sent to the API
+    pub tokens: usize,       // usage reported by the provider
 }
 
 #[async_trait::async_trait]
 pub trait Embedder: Send + Sync {
-    async fn embed_batch(&self, inputs: Vec<Chunk>) -> Result<Vec<Embedding>, String>;
+    async fn embed_batch(&self, inputs: Vec<Chunk>) -> Result<EmbeddedBatch, String>;
     async fn embed_question(&self, question: String) -> Result<Vec<f32>, String>;
 }
 
@@ -42,6 +27,8 @@ pub struct MistralEmbedder {
     pub endpoint: String,
     pub model: String,
     pub client: Client,
+    pub limiter: Arc<RateLimiter>,
+    pub retry: RetryPolicy,
 }
 
 #[derive(Serialize)]
@@ -53,6 +40,13 @@ struct MistralEmbeddingRequest<'a> {
 #[derive(Deserialize)]
 struct MistralEmbeddingResponse {
     data: Vec<MistralEmbeddingData>,
+    #[serde(default)]
+    usage: Option<EmbeddingUsage>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingUsage {
+    total_tokens: usize,
 }
 
 #[derive(Deserialize)]
@@ -75,50 +69,44 @@ impl MistralEmbedder {
             endpoint,
             model,
             client,
+            limiter: RateLimiter::for_provider("mistral", 60, 500_000),
+            retry: RetryPolicy::new(config::Config::from_env().embedding_max_retries),
         }
     }
+
+    async fn post(&self, body: &MistralEmbeddingRequest<'_>) -> Result<Response, String> {
+        rate_limit::send_with_retry(&self.limiter, &self.retry, count_tokens(body.input), || {
+            self.client
+                .post(&self.endpoint)
+                .header(AUTHORIZATION, format!("Bearer {}", self.api_key))
+                .header(CONTENT_TYPE, "application/json")
+                .json(body)
+        })
+        .await
+    }
 }
 
 #[async_trait::async_trait]
 impl Embedder for MistralEmbedder {
-    async fn embed_batch(&self, inputs: Vec<Chunk>) -> Result<Vec<Embedding>, String> {
-        let text_inputs: Vec<_> = inputs
-            .iter()
-            .map(|c| c.clone().text)
-            .filter(|s| {
-                !s.trim().is_empty()
-                    && s.len() <= MAX_SEQUENCE_LENGTH
-                    && s.is_char_boundary(s.len())
-            })
-            .collect();
-        if text_inputs.is_empty() {
-            let paths: Vec<_> = inputs.iter().map(|c| &c.path).collect();
-            return Err(format!("Empty input batch for files: {:?}", paths));
+    async fn embed_batch(&self, inputs: Vec<Chunk>) -> Result<EmbeddedBatch, String> {
+        let (inputs, skipped): (Vec<Chunk>, Vec<Chunk>) =
+            inputs.into_iter().partition(|c| utils::is_embeddable(&c.text));
+        if inputs.is_empty() {
+            return Ok(EmbeddedBatch {
+                embeddings: vec![],
+                skipped,
+                tokens: 0,
+            });
         }
+        let text_inputs: Vec<_> = inputs.iter().map(|c| c.text.clone()).collect();
         let req_body = MistralEmbeddingRequest {
             model: &self.model,
             input: &text_inputs,
         };
         let res = self
-            .client
-            .post(&self.endpoint)
-            .header(AUTHORIZATION, format!("Bearer {}", self.api_key))
-            .header(CONTENT_TYPE, "application/json")
-            .json(&req_body)
-            .send()
+            .post(&req_body)
             .await
-            .map_err(|e| format!("HTTP error: {e}"))?;
-        if !res.status().is_success() {
-            let status = res.status();
-            let body = res
-                .text()
-                .await
-                .unwrap_or_else(|e| format!("(error reading body: {e})"));
-            return Err(format!(
-                "Mistral API error: status {} — body: {}",
-                status, body
-            ));
-        }
+            .map_err(|e| format!("Mistral API error: {e}"))?;
         let parsed: MistralEmbeddingResponse = res
             .json()
             .await
@@ -130,21 +118,22 @@ impl Embedder for MistralEmbedder {
                 inputs.len()
             ));
         }
-        let mut result = Vec::new();
-
-        for (index, data) in parsed.data.into_iter().enumerate() {
-            result.push(Embedding::new(inputs[index].clone(), data.embedding));
-        }
+        let tokens = parsed.usage.map(|u| u.total_tokens).unwrap_or_default();
+        let embeddings = inputs
+            .into_iter()
+            .zip(parsed.data)
+            .map(|(chunk, data)| Embedding::new(chunk, data.embedding))
+            .collect();
 
-        Ok(result)
+        Ok(EmbeddedBatch {
+            embeddings,
+            skipped,
+            tokens,
+        })
     }
 
     async fn embed_question(&self, question: String) -> Result<Vec<f32>, String> {
-        let question = question;
-        if question.trim().is_empty()
-            || question.len() > MAX_SEQUENCE_LENGTH
-            || !question.is_char_boundary(question.len())
-        {
+        if !utils::is_embeddable(&question) {
             return Err(format!(
                 "Invalid chunk: empty, too long, or invalid char boundary: question={}",
                 question
@@ -157,26 +146,9 @@ impl Embedder for MistralEmbedder {
         };
 
         let res = self
-            .client
-            .post(&self.endpoint)
-            .header(AUTHORIZATION, format!("Bearer {}", self.api_key))
-            .header(CONTENT_TYPE, "application/json")
-            .json(&req_body)
-            .send()
+            .post(&req_body)
             .await
-            .map_err(|e| format!("HTTP error: {e}"))?;
-
-        if !res.status().is_success() {
-            let status = res.status();
-            let body = res
-                .text()
-                .await
-                .unwrap_or_else(|e| format!("(error reading body: {e})"));
-            return Err(format!(
-                "Mistral API error: status {} — body: {}",
-                status, body
-            ));
-        }
+            .map_err(|e| format!("Mistral API error: {e}"))?;
 
         let parsed: MistralEmbeddingResponse = res
             .json()
@@ -200,6 +172,8 @@ pub struct OpenAIEmbedder {
     pub endpoint: String,
     pub model: String,
     pub client: Client,
+    pub limiter: Arc<RateLimiter>,
+    pub retry: RetryPolicy,
 }
 
 #[derive(Serialize)]
@@ -212,6 +186,8 @@ struct OpenAIEmbeddingRequest<'a> {
 #[derive(Deserialize)]
 struct OpenAIEmbeddingResponse {
     data: Vec<OpenAIEmbeddingData>,
+    #[serde(default)]
+    usage: Option<EmbeddingUsage>,
 }
 
 #[derive(Deserialize)]
@@ -234,27 +210,36 @@ impl OpenAIEmbedder {
             endpoint,
             model,
             client,
+            limiter: RateLimiter::for_provider("openai", 3_000, 1_000_000),
+            retry: RetryPolicy::new(config::Config::from_env().embedding_max_retries),
         }
     }
+
+    async fn post(&self, body: &OpenAIEmbeddingRequest<'_>) -> Result<Response, String> {
+        rate_limit::send_with_retry(&self.limiter, &self.retry, count_tokens(body.input), || {
+            self.client
+                .post(&self.endpoint)
+                .header(AUTHORIZATION, format!("Bearer {}", self.api_key))
+                .header(CONTENT_TYPE, "application/json")
+                .json(body)
+        })
+        .await
+    }
 }
 
 #[async_trait::async_trait]
 impl Embedder for OpenAIEmbedder {
-    async fn embed_batch(&self, inputs: Vec<Chunk>) -> Result<Vec<Embedding>, String> {
-        let text_inputs: Vec<_> = inputs
-            .iter()
-            .map(|c| c.clone().text)
-            .filter(|s| {
-                !s.trim().is_empty()
-                    && s.len() <= MAX_SEQUENCE_LENGTH
-                    && s.is_char_boundary(s.len())
-            })
-            .collect();
-        
-        if text_inputs.is_empty() {
-            let paths: Vec<_> = inputs.iter().map(|c| &c.path).collect();
-            return Err(format!("Empty input batch for files: {:?}", paths));
+    async fn embed_batch(&self, inputs: Vec<Chunk>) -> Result<EmbeddedBatch, String> {
+        let (inputs, skipped): (Vec<Chunk>, Vec<Chunk>) =
+            inputs.into_iter().partition(|c| utils::is_embeddable(&c.text));
+        if inputs.is_empty() {
+            return Ok(EmbeddedBatch {
+                embeddings: vec![],
+                skipped,
+                tokens: 0,
+            });
         }
+        let text_inputs: Vec<_> = inputs.iter().map(|c| c.text.clone()).collect();
         
         let req_body = OpenAIEmbeddingRequest {
             model: &self.model,
@@ -263,26 +248,9 @@ impl Embedder for OpenAIEmbedder {
         };
         
         let res = self
-            .client
-            .post(&self.endpoint)
-            .header(AUTHORIZATION, format!("Bearer {}", self.api_key))
-            .header(CONTENT_TYPE, "application/json")
-            .json(&req_body)
-            .send()
+            .post(&req_body)
             .await
-            .map_err(|e| format!("HTTP error: {e}"))?;
-            
-        if !res.status().is_success() {
-            let status = res.status();
-            let body = res
-                .text()
-                .await
-                .unwrap_or_else(|e| format!("(error reading body: {e})"));
-            return Err(format!(
-                "OpenAI API error: status {} — body: {}",
-                status, body
-            ));
-        }
+            .map_err(|e| format!("OpenAI API error: {e}"))?;
         
         let parsed: OpenAIEmbeddingResponse = res
             .json()
@@ -297,19 +265,22 @@ impl Embedder for OpenAIEmbedder {
             ));
         }
         
-        let mut result = Vec::new();
-        for (index, data) in parsed.data.into_iter().enumerate() {
-            result.push(Embedding::new(inputs[index].clone(), data.embedding));
-        }
+        let tokens = parsed.usage.map(|u| u.total_tokens).unwrap_or_default();
+        let embeddings = inputs
+            .into_iter()
+            .zip(parsed.data)
+            .map(|(chunk, data)| Embedding::new(chunk, data.embedding))
+            .collect();
 
-        Ok(result)
+        Ok(EmbeddedBatch {
+            embeddings,
+            skipped,
+            tokens,
+        })
     }
 
     async fn embed_question(&self, question: String) -> Result<Vec<f32>, String> {
-        if question.trim().is_empty()
-            || question.len() > MAX_SEQUENCE_LENGTH
-            || !question.is_char_boundary(question.len())
-        {
+        if !utils::is_embeddable(&question) {
             return Err(format!(
                 "Invalid question: empty, too long, or invalid char boundary: question={}",
                 question
@@ -323,26 +294,9 @@ impl Embedder for OpenAIEmbedder {
         };
 
         let res = self
-            .client
-            .post(&self.endpoint)
-            .header(AUTHORIZATION, format!("Bearer {}", self.api_key))
-            .header(CONTENT_TYPE, "application/json")
-            .json(&req_body)
-            .send()
+            .post(&req_body)
             .await
-            .map_err(|e| format!("HTTP error: {e}"))?;
-
-        if !res.status().is_success() {
-            let status = res.status();
-            let body = res
-                .text()
-                .await
-                .unwrap_or_else(|e| format!("(error reading body: {e})"));
-            return Err(format!(
-                "OpenAI API error: status {} — body: {}",
-                status, body
-            ));
-        }
+            .map_err(|e| format!("OpenAI API error: {e}"))?;
 
         let parsed: OpenAIEmbeddingResponse = res
             .json()
@@ -361,6 +315,12 @@ impl Embedder for OpenAIEmbedder {
     }
 }
 
+/// Token count used against the tokens-per-minute budget.
+fn count_tokens(input: &[String]) -> usize {
+    let bpe = cl100k_base_singleton();
+    input.iter().map(|text| bpe.encode_ordinary(text).len()).sum()
+}
+
 pub fn create_embedder() -> Box<dyn Embedder> {
     let provider = std::env::var("EMBEDDING_PROVIDER")
         .unwrap_or_else(|_| "mistral".to_string())
@@ -368,6 +328,6 @@ pub fn create_embedder() -> Box<dyn Embedder> {
     
     match provider.as_str() {
         "openai" => Box::new(OpenAIEmbedder::from_env()),
-        "mistral" | _ => Box::new(MistralEmbedder::from_env()),
+        _ => Box::new(MistralEmbedder::from_env()),
     }
 }
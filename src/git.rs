@@ -0,0 +1,289 @@
+use git2::build::{CheckoutBuilder, RepoBuilder};
+use git2::{ObjectType, Repository, StatusOptions, TreeWalkMode, TreeWalkResult};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+
+/// Qdrant collection holding the index of `repo` at `git_ref`
+/// (the working tree when no ref is given).
+pub fn collection_name(repo: &str, git_ref: Option<&str>) -> String {
+    match git_ref {
+        None => repo.to_string(),
+        Some(git_ref) => {
+            // Qdrant refuses '/', ':' ... in collection names, and refs often contain '/'
+            let escaped: String = git_ref
+                .chars()
+                .map(|c| {
+                    if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_') {
+                        c
+                    } else {
+                        '_'
+                    }
+                })
+                .collect();
+            if escaped == git_ref {
+                return format!("{repo}@{git_ref}");
+            }
+            // Suffixe de hash du ref brut : release/1.2 et release_1.2 ne doivent pas partager une collection
+            let digest = format!("{:x}", Sha256::digest(git_ref.as_bytes()));
+            format!("{repo}@{escaped}-{}", &digest[..8])
+        }
+    }
+}
+
+/// Commit currently checked out in `repo_dir`, if it is a git repository.
+pub fn head_commit(repo_dir: &str) -> Option<String> {
+    let repo = Repository::open(repo_dir).ok()?;
+    let commit = repo.head().ok()?.peel_to_commit().ok()?;
+    Some(commit.id().to_string())
+}
+
+/// Content of the file at `path` (relative to the repo root) in the tree of `rev`,
+/// None when there is no such file.
+pub fn read_file_at_ref(repo_dir: &str, rev: &str, path: &str) -> Result<Option<Vec<u8>>, String> {
+    let repo = Repository::open(repo_dir).map_err(|e| format!("{repo_dir}: {}", e.message()))?;
+    let tree = repo
+        .revparse_single(rev)
+        .and_then(|obj| obj.peel_to_tree())
+        .map_err(|e| format!("Unknown ref {rev}: {}", e.message()))?;
+    let Ok(entry) = tree.get_path(Path::new(path)) else {
+        return Ok(None);
+    };
+    let blob = repo
+        .find_blob(entry.id())
+        .map_err(|e| format!("{path} at {rev}: {}", e.message()))?;
+    Ok(Some(blob.content().to_vec()))
+}
+
+/// Visit every file of the tree of `rev` (branch, tag or commit) straight from the
+/// object database, without touching the working tree. `visit` receives the path
+/// relative to the repo root, whether the file is executable, and a loader for the blob
+/// content, so skipped files are never read.
+/// Returns the resolved commit id.
+pub fn walk_ref(
+    repo_dir: &str,
+    rev: &str,
+    mut visit: impl FnMut(&str, bool, &dyn Fn() -> Result<Vec<u8>, String>),
+) -> Result<String, String> {
+    let repo = Repository::open(repo_dir).map_err(|e| format!("{repo_dir}: {}", e.message()))?;
+    let commit = repo
+        .revparse_single(rev)
+        .and_then(|obj| obj.peel_to_commit())
+        .map_err(|e| format!("Unknown ref {rev}: {}", e.message()))?;
+    let tree = commit.tree().map_err(|e| e.message().to_string())?;
+
+    tree.walk(TreeWalkMode::PreOrder, |dir, entry| {
+        if entry.kind() != Some(ObjectType::Blob) {
+            return TreeWalkResult::Ok;
+        }
+        let Some(name) = entry.name() else {
+            return TreeWalkResult::Ok;
+        };
+        let path = format!("{dir}{name}");
+        let load = || {
+            repo.find_blob(entry.id())
+                .map(|blob| blob.content().to_vec())
+                .map_err(|e| e.message().to_string())
+        };
+        visit(&path, entry.filemode() == 0o100755, &load);
+        TreeWalkResult::Ok
+    })
+    .map_err(|e| e.message().to_string())?;
+
+    Ok(commit.id().to_string())
+}
+
+/// Name of the clone directory for `url`: its last path segment without `.git`.
+pub fn repo_name_from_url(url: &str) -> Option<String> {
+    let name = url
+        .trim_end_matches('/')
+        .rsplit(['/', ':', '\\'])
+        .next()?
+        .trim_end_matches(".git");
+    is_valid_repo_name(name).then(|| name.to_string())
+}
+
+pub fn is_valid_repo_name(name: &str) -> bool {
+    !name.is_empty()
+        && name != "."
+        && name != ".."
+        && !name.contains(['/', '\\', '@'])
+}
+
+/// Config key set on the clones made by `clone_or_fetch`, the only ones it force checks out.
+const MANAGED_KEY: &str = "better-deep-wiki.managed";
+
+/// Clone `url` (https, ssh, `file://` or a local path, bare or not) into `dest`, or fetch
+/// it when `dest` already holds a clean clone of `url`, then check out `git_ref` (the current
+/// branch, moved to its fetched head, when absent). Returns the checked out commit id.
+pub fn clone_or_fetch(url: &str, dest: &Path, git_ref: Option<&str>) -> Result<String, String> {
+    let (repo, managed) = if dest.exists() {
+        let repo = Repository::open(dest).map_err(|e| {
+            format!("{} exists and is not a git repository: {}", dest.display(), e.message())
+        })?;
+        let mut remote = repo
+            .find_remote("origin")
+            .map_err(|_| format!("{} has no origin remote", dest.display()))?;
+        let origin = remote.url().unwrap_or_default().to_string();
+        if origin.trim_end_matches('/') != url.trim_end_matches('/') {
+            return Err(format!("{} is a clone of {origin}, not of {url}", dest.display()));
+        }
+        let mut options = StatusOptions::new();
+        options.include_untracked(false).include_ignored(false);
+        let dirty = repo
+            .statuses(Some(&mut options))
+            .map_err(|e| e.message().to_string())?
+            .len();
+        if dirty > 0 {
+            return Err(format!(
+                "{} has {dirty} uncommitted change(s), commit or discard them first",
+                dest.display()
+            ));
+        }
+        remote
+            .fetch(
+                &["+refs/heads/*:refs/remotes/origin/*", "+refs/tags/*:refs/tags/*"],
+                None,
+                None,
+            )
+            .map_err(|e| format!("Fetch of {url} failed: {}", e.message()))?;
+        drop(remote);
+        let managed = repo
+            .config()
+            .and_then(|config| config.get_bool(MANAGED_KEY))
+            .unwrap_or(false);
+        (repo, managed)
+    } else {
+        let repo = RepoBuilder::new()
+            .clone(url, dest)
+            .map_err(|e| format!("Clone of {url} failed: {}", e.message()))?;
+        repo.config()
+            .and_then(|mut config| config.set_bool(MANAGED_KEY, true))
+            .map_err(|e| e.message().to_string())?;
+        (repo, true)
+    };
+
+    checkout(&repo, git_ref, managed).map_err(|e| format!("Checkout failed: {}", e.message()))
+}
+
+/// `force` overwrites local changes; otherwise a checkout that would lose them fails.
+fn checkout(repo: &Repository, git_ref: Option<&str>, force: bool) -> Result<String, git2::Error> {
+    let branch = match git_ref {
+        Some(git_ref) => Some(git_ref.to_string()),
+        None => repo
+            .head()
+            .ok()
+            .filter(|head| head.is_branch())
+            .and_then(|head| head.shorthand().map(str::to_string)),
+    };
+    let options = || {
+        let mut options = CheckoutBuilder::new();
+        if force {
+            options.force();
+        } else {
+            options.safe();
+        }
+        options
+    };
+
+    // A branch of origin: the local branch is moved onto it, so that scanning
+    // the ref later reads the fetched commit and not a stale local one
+    if let Some(branch) = branch {
+        if let Ok(remote) = repo.find_reference(&format!("refs/remotes/origin/{branch}")) {
+            let commit = remote.peel_to_commit()?;
+            // Checkout before moving HEAD: a safe checkout compares against the current HEAD
+            repo.checkout_tree(commit.as_object(), Some(&mut options()))?;
+            let local = format!("refs/heads/{branch}");
+            repo.reference(&local, commit.id(), true, "better-deep-wiki: fetch")?;
+            repo.set_head(&local)?;
+            return Ok(commit.id().to_string());
+        }
+    }
+
+    match git_ref {
+        // Tag or commit
+        Some(git_ref) => {
+            let commit = repo.revparse_single(git_ref)?.peel_to_commit()?;
+            repo.checkout_tree(commit.as_object(), Some(&mut options()))?;
+            repo.set_head_detached(commit.id())?;
+            Ok(commit.id().to_string())
+        }
+        None => Ok(repo.head()?.peel_to_commit()?.id().to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collection_name() {
+        assert_eq!(collection_name("repo", None), "repo");
+        assert_eq!(collection_name("repo", Some("main")), "repo@main");
+        assert_eq!(collection_name("repo", Some("release_1.2")), "repo@release_1.2");
+        let escaped = collection_name("repo", Some("release/1.2"));
+        assert!(escaped.starts_with("repo@release_1.2-"));
+        assert_ne!(escaped, collection_name("repo", Some("release:1.2")));
+    }
+
+    #[test]
+    fn test_repo_name_from_url() {
+        assert_eq!(repo_name_from_url("https://github.com/acme/repo.git").as_deref(), Some("repo"));
+        assert_eq!(repo_name_from_url("git@github.com:acme/repo.git").as_deref(), Some("repo"));
+        assert_eq!(repo_name_from_url("file:///srv/git/repo/").as_deref(), Some("repo"));
+        assert_eq!(repo_name_from_url("/srv/git/.."), None);
+    }
+
+    #[test]
+    fn test_clone_then_fetch_from_bare_repo() {
+        let root = std::env::temp_dir().join(format!("bdw-clone-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let origin = Repository::init_bare(root.join("origin.git")).unwrap();
+        let signature = git2::Signature::now("test", "test@example.com").unwrap();
+
+        let commit_file = |content: &str, parents: &[&git2::Commit]| {
+            let blob = origin.blob(content.as_bytes()).unwrap();
+            let mut tree = origin.treebuilder(None).unwrap();
+            tree.insert("main.rs", blob, 0o100644).unwrap();
+            let tree = origin.find_tree(tree.write().unwrap()).unwrap();
+            origin
+                .commit(Some("HEAD"), &signature, &signature, content, &tree, parents)
+                .unwrap()
+        };
+
+        let first = commit_file("fn v1() {}", &[]);
+        let url = format!("file://{}", root.join("origin.git").display());
+        let dest = root.join("clone");
+
+        let cloned = clone_or_fetch(&url, &dest, None).unwrap();
+        assert_eq!(cloned, first.to_string());
+        assert_eq!(std::fs::read_to_string(dest.join("main.rs")).unwrap(), "fn v1() {}");
+
+        let parent = origin.find_commit(first).unwrap();
+        let second = commit_file("fn v2() {}", &[&parent]);
+        let fetched = clone_or_fetch(&url, &dest, None).unwrap();
+        assert_eq!(fetched, second.to_string());
+        assert_eq!(std::fs::read_to_string(dest.join("main.rs")).unwrap(), "fn v2() {}");
+
+        let pinned = clone_or_fetch(&url, &dest, Some(&first.to_string())).unwrap();
+        assert_eq!(pinned, first.to_string());
+        assert_eq!(std::fs::read_to_string(dest.join("main.rs")).unwrap(), "fn v1() {}");
+
+        // Local edits are never overwritten
+        std::fs::write(dest.join("main.rs"), "fn local() {}").unwrap();
+        assert!(clone_or_fetch(&url, &dest, None).unwrap_err().contains("uncommitted"));
+        assert_eq!(std::fs::read_to_string(dest.join("main.rs")).unwrap(), "fn local() {}");
+
+        // A clone made outside the tool is updated with a safe checkout
+        let manual = root.join("manual");
+        Repository::clone(&url, &manual).unwrap();
+        let parent = origin.find_commit(second).unwrap();
+        let third = commit_file("fn v3() {}", &[&parent]);
+        assert_eq!(clone_or_fetch(&url, &manual, None).unwrap(), third.to_string());
+        assert_eq!(std::fs::read_to_string(manual.join("main.rs")).unwrap(), "fn v3() {}");
+
+        let other = format!("file://{}", root.join("other.git").display());
+        assert!(clone_or_fetch(&other, &dest, None).unwrap_err().contains("is a clone of"));
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -1,32 +1,59 @@
 use crate::types::{Chunk, ChunkType};
-use crate::{chunking, config};
+use crate::{chunking, parsing};
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
+use streaming_iterator::StreamingIterator;
 use tree_sitter::{Language, Parser, Query, QueryCursor};
 
+/// Functions, methods named after the self type of their impl or their trait (`Config::load`),
+/// structs, enums, traits, impls, inline modules, `macro_rules!`, type aliases and item-level
+/// `const` / `static`, all qualified by their inline modules (`helpers::clamp`). Trait impls are
+/// named `<Wrapper as Display>`, and their methods `<Wrapper as Display>::fmt`.
 pub fn chunk_rust(
     content: &str,
     file_path: &Path,
-    max_chunk_size: usize,
+    splitter: &chunking::TextSplitter,
     enc: &tiktoken_rs::CoreBPE,
 ) -> Result<Vec<Chunk>, String> {
-    let language = tree_sitter_rust::language();
+    let language: Language = tree_sitter_rust::LANGUAGE.into();
 
     let queries = vec![
         r#"(function_item name: (identifier) @name body: (block) @body) @item"#,
-        r#"(struct_item name: (type_identifier) @name body: (field_declaration_list) @body) @item"#,
-        r#"(impl_item type: (type_identifier) @name body: (declaration_list) @body) @item"#,
+        r#"(struct_item name: (type_identifier) @name) @item"#,
+        r#"(enum_item name: (type_identifier) @name body: (enum_variant_list) @body) @item"#,
+        r#"(trait_item name: (type_identifier) @name body: (declaration_list) @body) @item"#,
+        // Le type sans ses paramètres génériques : `impl<T> Foo<T>` → `Foo`
+        r#"(impl_item trait: (_) @trait type: [(type_identifier) (scoped_type_identifier)] @name body: (declaration_list) @body) @item"#,
+        r#"(impl_item trait: (_) @trait type: (generic_type type: (_) @name) body: (declaration_list) @body) @item"#,
+        r#"(impl_item trait: (_) @trait type: (_) @name body: (declaration_list) @body) @item"#,
+        r#"(impl_item !trait type: [(type_identifier) (scoped_type_identifier)] @name body: (declaration_list) @body) @item"#,
+        r#"(impl_item !trait type: (generic_type type: (_) @name) body: (declaration_list) @body) @item"#,
+        r#"(impl_item !trait type: (_) @name body: (declaration_list) @body) @item"#,
+        r#"(mod_item name: (identifier) @name body: (declaration_list) @body) @item"#,
+        r#"(macro_definition name: (identifier) @name) @item"#,
+        r#"(type_item name: (type_identifier) @name) @item"#,
+        // Pas les constantes locales d'une fonction
+        r#"[(source_file [(const_item name: (identifier) @name) (static_item name: (identifier) @name)] @item) (declaration_list [(const_item name: (identifier) @name) (static_item name: (identifier) @name)] @item)]"#,
     ];
 
-    parse_with_queries(&language, queries, content, file_path, max_chunk_size, enc)
+    parse_with_scopes(
+        &language,
+        queries,
+        &[("mod_item", "::"), ("impl_item", "::"), ("trait_item", "::")],
+        content,
+        file_path,
+        splitter,
+        enc,
+    )
 }
 
 pub fn chunk_javascript(
     content: &str,
     file_path: &Path,
-    max_chunk_size: usize,
+    splitter: &chunking::TextSplitter,
     enc: &tiktoken_rs::CoreBPE,
 ) -> Result<Vec<Chunk>, String> {
-    let language = tree_sitter_javascript::language();
+    let language: Language = tree_sitter_javascript::LANGUAGE.into();
 
     let queries = vec![
         r#"(function_declaration name: (identifier) @name body: (statement_block) @body) @item"#,
@@ -35,16 +62,16 @@ pub fn chunk_javascript(
         r#"(method_definition name: (property_identifier) @name body: (statement_block) @body) @item"#,
     ];
 
-    parse_with_queries(&language, queries, content, file_path, max_chunk_size, enc)
+    parse_with_queries(&language, queries, content, file_path, splitter, enc)
 }
 
 pub fn chunk_tsx(
     content: &str,
     file_path: &Path,
-    max_chunk_size: usize,
+    splitter: &chunking::TextSplitter,
     enc: &tiktoken_rs::CoreBPE,
 ) -> Result<Vec<Chunk>, String> {
-    let language = tree_sitter_typescript::language_tsx();
+    let language: Language = tree_sitter_typescript::LANGUAGE_TSX.into();
 
     let queries = vec![
         r#"(function_declaration name: (identifier) @name body: (statement_block) @body) @item"#,
@@ -54,16 +81,16 @@ pub fn chunk_tsx(
         r#"(method_definition name: (property_identifier) @name body: (statement_block) @body) @item"#,
     ];
 
-    parse_with_queries(&language, queries, content, file_path, max_chunk_size, enc)
+    parse_with_queries(&language, queries, content, file_path, splitter, enc)
 }
 
 pub fn chunk_java(
     content: &str,
     file_path: &Path,
-    max_chunk_size: usize,
+    splitter: &chunking::TextSplitter,
     enc: &tiktoken_rs::CoreBPE,
 ) -> Result<Vec<Chunk>, String> {
-    let language = tree_sitter_java::language();
+    let language: Language = tree_sitter_java::LANGUAGE.into();
 
     let queries = vec![
         r#"(class_declaration name: (identifier) @name body: (class_body) @body) @item"#,
@@ -71,7 +98,236 @@ pub fn chunk_java(
         r#"(interface_declaration name: (identifier) @name body: (interface_body) @body) @item"#,
     ];
 
-    parse_with_queries(&language, queries, content, file_path, max_chunk_size, enc)
+    parse_with_queries(&language, queries, content, file_path, splitter, enc)
+}
+
+/// Module-level functions and classes, with their decorators, and methods whose parent is
+/// their class, named `Class.method`. Docstrings are part of the body, so they stay with their definition.
+pub fn chunk_python(
+    content: &str,
+    file_path: &Path,
+    splitter: &chunking::TextSplitter,
+    enc: &tiktoken_rs::CoreBPE,
+) -> Result<Vec<Chunk>, String> {
+    let language: Language = tree_sitter_python::LANGUAGE.into();
+
+    // `async def` est aussi une function_definition
+    let queries = vec![
+        r#"(module (function_definition name: (identifier) @name body: (block) @body) @item)"#,
+        r#"(module (decorated_definition definition: (function_definition name: (identifier) @name body: (block) @body)) @item)"#,
+        r#"(module (class_definition name: (identifier) @name body: (block) @body) @item)"#,
+        r#"(module (decorated_definition definition: (class_definition name: (identifier) @name body: (block) @body)) @item)"#,
+        r#"(class_definition name: (identifier) @parent body: (block (function_definition name: (identifier) @name body: (block) @body) @item))"#,
+        r#"(class_definition name: (identifier) @parent body: (block (decorated_definition definition: (function_definition name: (identifier) @name body: (block) @body)) @item))"#,
+    ];
+
+    parse_with_queries(&language, queries, content, file_path, splitter, enc)
+}
+
+/// Functions, methods whose parent is their receiver type (`Server.Start`), struct and
+/// interface types, and top-level const / var declarations named after their first name.
+/// Each spec of a `type ( … )`, `const ( … )` or `var ( … )` block is a chunk of its own.
+pub fn chunk_go(
+    content: &str,
+    file_path: &Path,
+    splitter: &chunking::TextSplitter,
+    enc: &tiktoken_rs::CoreBPE,
+) -> Result<Vec<Chunk>, String> {
+    let language: Language = tree_sitter_go::LANGUAGE.into();
+
+    let queries = vec![
+        r#"(function_declaration name: (identifier) @name body: (block) @body) @item"#,
+        r#"(method_declaration
+            receiver: (parameter_list (parameter_declaration type: [
+                (type_identifier) @parent
+                (pointer_type (type_identifier) @parent)
+                (generic_type type: (type_identifier) @parent)
+                (pointer_type (generic_type type: (type_identifier) @parent))
+            ]))
+            name: (field_identifier) @name body: (block) @body) @item"#,
+        // Une déclaration seule avec son mot-clé, chaque spec d'un bloc `type ( … )` à part
+        r#"((type_declaration (type_spec name: (type_identifier) @name type: (struct_type) @body)) @item (#not-match? @item "^type[[:space:]]*[(]"))"#,
+        r#"(type_declaration "(" (type_spec name: (type_identifier) @name type: (struct_type) @body) @item)"#,
+        r#"((type_declaration (type_spec name: (type_identifier) @name type: (interface_type) @body)) @item (#not-match? @item "^type[[:space:]]*[(]"))"#,
+        r#"(type_declaration "(" (type_spec name: (type_identifier) @name type: (interface_type) @body) @item)"#,
+        r#"(source_file (const_declaration (const_spec name: (identifier) @name)) @item (#not-match? @item "^const[[:space:]]*[(]"))"#,
+        r#"(source_file (const_declaration "(" (const_spec name: (identifier) @name) @item))"#,
+        r#"(source_file (var_declaration (var_spec name: (identifier) @name)) @item (#not-match? @item "^var[[:space:]]*[(]"))"#,
+        r#"(source_file (var_declaration "(" (var_spec name: (identifier) @name) @item))"#,
+    ];
+
+    parse_with_queries(&language, queries, content, file_path, splitter, enc)
+}
+
+// Le déclarateur d'une fonction, éventuellement derrière le `*` du type de retour
+const C_FUNCTION_DECLARATOR: &str = r#"
+    (function_declarator declarator: (_) @name)
+    (pointer_declarator declarator: (function_declarator declarator: (_) @name))"#;
+// ou, en C++, derrière un `&`
+const CPP_REFERENCE_DECLARATOR: &str = r#"
+    (reference_declarator (function_declarator declarator: (_) @name))"#;
+
+/// Function definitions, structs (typedef'd ones too) and, in headers, function prototypes
+/// as `Declaration` chunks.
+pub fn chunk_c(
+    content: &str,
+    file_path: &Path,
+    splitter: &chunking::TextSplitter,
+    enc: &tiktoken_rs::CoreBPE,
+) -> Result<Vec<Chunk>, String> {
+    let language: Language = tree_sitter_c::LANGUAGE.into();
+    let queries = c_family_queries(C_FUNCTION_DECLARATOR, &[], parsing::is_header(file_path));
+    let queries = queries.iter().map(String::as_str).collect();
+
+    parse_with_scopes(&language, queries, &[], content, file_path, splitter, enc)
+}
+
+/// Like `chunk_c`, plus classes. Names are qualified by their enclosing namespaces and
+/// classes (`net::Server::start`); methods are the functions of a class, or defined out of
+/// line (`Server::start`).
+pub fn chunk_cpp(
+    content: &str,
+    file_path: &Path,
+    splitter: &chunking::TextSplitter,
+    enc: &tiktoken_rs::CoreBPE,
+) -> Result<Vec<Chunk>, String> {
+    let language: Language = tree_sitter_cpp::LANGUAGE.into();
+    let class = r#"(class_specifier name: (type_identifier) @name body: (field_declaration_list) @body) @item"#;
+    let declarator = format!("{C_FUNCTION_DECLARATOR}{CPP_REFERENCE_DECLARATOR}");
+    let queries = c_family_queries(&declarator, &[class], parsing::is_header(file_path));
+    let queries = queries.iter().map(String::as_str).collect();
+
+    parse_with_scopes(
+        &language,
+        queries,
+        &[("namespace_definition", "::"), ("class_specifier", "::"), ("struct_specifier", "::")],
+        content,
+        file_path,
+        splitter,
+        enc,
+    )
+}
+
+/// Classes, records, structs and interfaces, with their methods, constructors and properties,
+/// named after their namespace and enclosing types (`Api.UsersController.Get`). Attribute lists
+/// stay in the text of the chunk and are recorded in `attributes`.
+pub fn chunk_csharp(
+    content: &str,
+    file_path: &Path,
+    splitter: &chunking::TextSplitter,
+    enc: &tiktoken_rs::CoreBPE,
+) -> Result<Vec<Chunk>, String> {
+    let language: Language = tree_sitter_c_sharp::LANGUAGE.into();
+
+    let queries = vec![
+        r#"(class_declaration name: (identifier) @name body: (declaration_list) @body) @item"#,
+        r#"(record_declaration name: (identifier) @name) @item"#,
+        r#"(struct_declaration name: (identifier) @name body: (declaration_list) @body) @item"#,
+        r#"(interface_declaration name: (identifier) @name body: (declaration_list) @body) @item"#,
+        r#"(method_declaration name: (identifier) @name) @item"#,
+        r#"(constructor_declaration name: (identifier) @name body: (_) @body) @item"#,
+        r#"(property_declaration name: (identifier) @name) @item"#,
+    ];
+
+    parse_with_scopes(
+        &language,
+        queries,
+        &[
+            ("namespace_declaration", "."),
+            ("file_scoped_namespace_declaration", "."),
+            ("class_declaration", "."),
+            ("record_declaration", "."),
+            ("struct_declaration", "."),
+            ("interface_declaration", "."),
+        ],
+        content,
+        file_path,
+        splitter,
+        enc,
+    )
+}
+
+/// Modules, classes and methods, named after their enclosing modules and classes the Ruby
+/// way: `Admin::UsersController#index`, and `Admin::User.find` for singleton methods, defined
+/// with `def self.find` or in a `class << self` block.
+pub fn chunk_ruby(
+    content: &str,
+    file_path: &Path,
+    splitter: &chunking::TextSplitter,
+    enc: &tiktoken_rs::CoreBPE,
+) -> Result<Vec<Chunk>, String> {
+    let language: Language = tree_sitter_ruby::LANGUAGE.into();
+
+    // `@instance` / `@singleton` : membres joints à leur classe par `#` / `.`
+    let queries = vec![
+        r#"(module name: (_) @name) @item"#,
+        r#"(class name: (_) @name) @item"#,
+        r#"(singleton_method object: (self) name: (_) @name) @item @singleton"#,
+        r#"(singleton_class value: (self) body: (body_statement (method name: (_) @name) @item @singleton))"#,
+        r#"(class body: (body_statement (method name: (_) @name) @item @instance))"#,
+        r#"(module body: (body_statement (method name: (_) @name) @item @instance))"#,
+        // `included do … end`, `Struct.new do … end`
+        r#"(do_block body: (body_statement (method name: (_) @name) @item @instance))"#,
+        r#"(program (method name: (_) @name) @item)"#,
+    ];
+
+    parse_with_scopes(
+        &language,
+        queries,
+        &[("module", "::"), ("class", "::")],
+        content,
+        file_path,
+        splitter,
+        enc,
+    )
+}
+
+/// Classes, interfaces and traits with their methods, static or not, and functions, named
+/// after their namespace and class: `App\Http\Controllers\UserController::store`.
+pub fn chunk_php(
+    content: &str,
+    file_path: &Path,
+    splitter: &chunking::TextSplitter,
+    enc: &tiktoken_rs::CoreBPE,
+) -> Result<Vec<Chunk>, String> {
+    let language: Language = tree_sitter_php::LANGUAGE_PHP.into();
+
+    let queries = vec![
+        r#"(class_declaration name: (name) @name body: (declaration_list) @body) @item"#,
+        r#"(interface_declaration name: (name) @name body: (declaration_list) @body) @item"#,
+        r#"(trait_declaration name: (name) @name body: (declaration_list) @body) @item"#,
+        r#"(method_declaration name: (name) @name body: (compound_statement) @body) @item"#,
+        r#"(function_definition name: (name) @name body: (compound_statement) @body) @item"#,
+    ];
+
+    parse_with_scopes(
+        &language,
+        queries,
+        &[
+            ("namespace_definition", "\\"),
+            ("class_declaration", "::"),
+            ("interface_declaration", "::"),
+            ("trait_declaration", "::"),
+        ],
+        content,
+        file_path,
+        splitter,
+        enc,
+    )
+}
+
+/// `declarator`: alternatives for the declarator of a function.
+fn c_family_queries(declarator: &str, extra: &[&str], header: bool) -> Vec<String> {
+    let mut queries = vec![
+        format!("(function_definition declarator: [{declarator}] body: (compound_statement) @body) @item"),
+        r#"(struct_specifier name: (type_identifier) @name body: (field_declaration_list) @body) @item"#.to_string(),
+        r#"(type_definition type: (struct_specifier !name body: (field_declaration_list) @body) declarator: (type_identifier) @name) @item"#.to_string(),
+    ];
+    queries.extend(extra.iter().map(|query| query.to_string()));
+    if header {
+        queries.push(format!("(declaration declarator: [{declarator}]) @item"));
+    }
+    queries
 }
 
 fn parse_with_queries(
@@ -79,38 +335,48 @@ fn parse_with_queries(
     queries: Vec<&str>,
     content: &str,
     file_path: &Path,
-    max_chunk_size: usize,
+    splitter: &chunking::TextSplitter,
+    enc: &tiktoken_rs::CoreBPE,
+) -> Result<Vec<Chunk>, String> {
+    parse_with_scopes(language, queries, &[], content, file_path, splitter, enc)
+}
+
+/// `scopes`: kinds of the nodes whose name prefixes the name of the chunks they enclose, with
+/// the separator that follows it, e.g. `("namespace_definition", "::")` for `outer::inner::name`.
+/// A function enclosed in anything but a namespace is a method.
+fn parse_with_scopes(
+    language: &Language,
+    queries: Vec<&str>,
+    scopes: &[(&str, &str)],
+    content: &str,
+    file_path: &Path,
+    splitter: &chunking::TextSplitter,
     enc: &tiktoken_rs::CoreBPE,
 ) -> Result<Vec<Chunk>, String> {
     let mut parser = Parser::new();
-    if parser.set_language(*language).is_err() {
+    if parser.set_language(language).is_err() {
         return Ok(vec![]);
     }
 
-    let config = config::Config::from_env();
-    let splitter = chunking::TextSplitter {
-        chunk_size: config.chunk_size,
-        chunk_overlap: config.chunk_overlap,
-    };
-
     let tree = match parser.parse(content, None) {
         Some(tree) => tree,
         None => return Ok(vec![]),
     };
 
-    let mut chunks = Vec::new();
-    let mut chunk_index = 0;
+    let mut items = Vec::new();
+    // Un même nœud peut correspondre plusieurs fois, e.g. un bloc const par nom déclaré
+    let mut seen = HashSet::new();
 
     for query_str in queries {
-        let query = match Query::new(*language, query_str) {
+        let query = match Query::new(language, query_str) {
             Ok(q) => q,
             Err(_) => continue,
         };
 
         let mut cursor = QueryCursor::new();
-        let matches = cursor.matches(&query, tree.root_node(), content.as_bytes());
+        let mut matches = cursor.matches(&query, tree.root_node(), content.as_bytes());
 
-        for m in matches {
+        while let Some(m) = matches.next() {
             if let Some(capture) = m
                 .captures
                 .iter()
@@ -119,47 +385,203 @@ fn parse_with_queries(
                 let node = capture.node;
                 let start_byte = node.start_byte();
                 let end_byte = node.end_byte();
-                if start_byte >= end_byte || end_byte > content.len() {
+                if start_byte >= end_byte || end_byte > content.len() || !seen.insert((start_byte, end_byte)) {
                     continue;
                 }
 
-                let chunk_text = &content[start_byte..end_byte];
-                let start_line = node.start_position().row + 1;
-                let end_line = node.end_position().row + 1;
-                let function_name = extract_function_name(&m.captures, &query, content);
-                let chunk_type = determine_chunk_type(query_str);
-
-                if enc.encode_ordinary(chunk_text).len() > max_chunk_size {
-                    let sub_chunks = split_large_chunk(
-                        chunk_text,
-                        file_path,
-                        start_line,
-                        chunk_index,
-                        &function_name,
-                        chunk_type.clone(),
-                        &splitter,
-                        enc,
-                    )?;
-                    chunks.extend(sub_chunks);
-                } else {
-                    chunks.push(Chunk {
-                        path: file_path.to_string_lossy().to_string(),
-                        chunk_index: chunk_index.to_string(),
-                        chunk_start_line: start_line,
-                        chunk_end_line: end_line,
-                        text: chunk_text.to_string(),
-                        function_name,
-                        chunk_type,
-                    });
-                    chunk_index += 1;
+                let mut function_name = extract_function_name(m.captures, &query, content);
+                let mut chunk_type = determine_chunk_type(query_str);
+                let enclosing = enclosing_scopes(node, scopes, content);
+                // Défini dans sa classe, ou hors de sa classe comme `Server::start`
+                let in_class = enclosing.iter().any(|(kind, _, _)| !kind.contains("namespace") && *kind != "mod_item")
+                    || (!scopes.is_empty() && function_name.as_ref().is_some_and(|name| name.contains("::")));
+                if in_class && matches!(chunk_type, ChunkType::Function) {
+                    chunk_type = ChunkType::Method;
+                }
+                if let Some((_, last_separator, _)) = enclosing.last() {
+                    let mut prefix: String = enclosing.iter().map(|(_, separator, name)| format!("{name}{separator}")).collect();
+                    // Membre d'instance ou de classe à la Ruby : `Users#index`, `Users.find`
+                    let has_capture = |name: &str| m.captures.iter().any(|c| query.capture_names()[c.index as usize] == name);
+                    let member_separator = match (has_capture("singleton"), has_capture("instance")) {
+                        (true, _) => Some("."),
+                        (false, true) => Some("#"),
+                        (false, false) => None,
+                    };
+                    if let Some(member_separator) = member_separator {
+                        prefix.truncate(prefix.len() - last_separator.len());
+                        prefix.push_str(member_separator);
+                    }
+                    function_name = function_name.map(|name| format!("{prefix}{name}"));
                 }
+                let attributes = attributes_of(node, scopes, content);
+
+                items.push(Item { node, function_name, chunk_type, attributes });
+            }
+        }
+    }
+
+    // Le parent d'un item est son plus proche ancêtre dans l'arbre qui est aussi un item
+    let item_of: HashMap<usize, usize> = items.iter().enumerate().map(|(index, item)| (item.node.id(), index)).collect();
+    let parents: Vec<Option<usize>> = items
+        .iter()
+        .map(|item| {
+            let mut ancestor = item.node.parent();
+            while let Some(node) = ancestor {
+                if let Some(&parent) = item_of.get(&node.id()) {
+                    return Some(parent);
+                }
+                ancestor = node.parent();
+            }
+            None
+        })
+        .collect();
+    let mut children: Vec<Vec<tree_sitter::Node>> = vec![Vec::new(); items.len()];
+    for (item, parent) in items.iter().zip(&parents) {
+        if let Some(parent) = parent {
+            children[*parent].push(item.node);
+        }
+    }
+
+    let mut top_level: Vec<std::ops::Range<usize>> = items
+        .iter()
+        .zip(&parents)
+        .filter(|(_, parent)| parent.is_none())
+        .map(|(item, _)| item.node.byte_range())
+        .collect();
+    top_level.sort_by_key(|range| range.start);
+    let first_gap_index = items.len();
+    let mut chunks = Vec::new();
+    for ((chunk_index, item), children) in items.into_iter().enumerate().zip(children) {
+        let chunk_text = skeleton(item.node, &children, content);
+        let start_line = item.node.start_position().row + 1;
+        let end_line = item.node.end_position().row + 1;
+        let parent_id = parents[chunk_index].map(|parent| parent.to_string()).unwrap_or_default();
+
+        // Les enfants d'un squelette découpé renvoient à sa première partie, cf. `utils::assign_point_ids`
+        if enc.encode_ordinary(&chunk_text).len() > splitter.chunk_size {
+            let mut sub_chunks = split_large_chunk(
+                &chunk_text,
+                file_path,
+                start_line,
+                chunk_index,
+                &item.function_name,
+                item.chunk_type,
+                &item.attributes,
+                splitter,
+                enc,
+            )?;
+            for sub_chunk in &mut sub_chunks {
+                sub_chunk.parent_id = parent_id.clone();
+            }
+            chunks.extend(sub_chunks);
+        } else {
+            chunks.push(Chunk {
+                path: file_path.to_string_lossy().to_string(),
+                chunk_index: chunk_index.to_string(),
+                chunk_start_line: start_line,
+                chunk_end_line: end_line,
+                text: chunk_text,
+                function_name: item.function_name,
+                chunk_type: item.chunk_type,
+                attributes: item.attributes,
+                parent_id,
+                ..Default::default()
+            });
+        }
+    }
+
+    // Le code hors de tout item (imports, constantes, script, `if __name__ == "__main__":`)
+    let mut cursor = 0;
+    let mut gap_index = first_gap_index;
+    for range in top_level.into_iter().chain(std::iter::once(content.len()..content.len())) {
+        if range.start > cursor {
+            if let Some(gap_chunks) = gap_chunks(content, cursor..range.start, gap_index, file_path, splitter, enc)? {
+                chunks.extend(gap_chunks);
+                gap_index += 1;
             }
         }
+        cursor = cursor.max(range.end);
     }
 
     Ok(chunks)
 }
 
+/// Line chunks of the code in `gap`, between two top-level items; None when it holds
+/// nothing but blanks and punctuation, e.g. the `}` closing a namespace.
+fn gap_chunks(
+    content: &str,
+    gap: std::ops::Range<usize>,
+    chunk_index: usize,
+    file_path: &Path,
+    splitter: &chunking::TextSplitter,
+    enc: &tiktoken_rs::CoreBPE,
+) -> Result<Option<Vec<Chunk>>, String> {
+    let gap_text = &content[gap.clone()];
+    let text = gap_text.trim();
+    // Ni ponctuation seule, ni l'ouverture d'un bloc dont chaque élément est un item (`const (`)
+    let block_opener = text
+        .strip_suffix(['(', '{'])
+        .is_some_and(|keyword| keyword.trim_end().chars().all(|c| c.is_alphanumeric() || c == '_'));
+    if !text.chars().any(char::is_alphanumeric) || block_opener {
+        return Ok(None);
+    }
+    let start = gap.start + (gap_text.len() - gap_text.trim_start().len());
+    let start_line = content[..start].matches('\n').count() + 1;
+
+    if enc.encode_ordinary(text).len() <= splitter.chunk_size {
+        return Ok(Some(vec![Chunk {
+            path: file_path.to_string_lossy().to_string(),
+            chunk_index: chunk_index.to_string(),
+            chunk_start_line: start_line,
+            chunk_end_line: start_line + text.matches('\n').count(),
+            text: text.to_string(),
+            chunk_type: ChunkType::LineChunk,
+            ..Default::default()
+        }]));
+    }
+    let mut sub_chunks = splitter.split_text(text, file_path.to_string_lossy().to_string(), enc)?;
+    for (sub_index, sub_chunk) in sub_chunks.iter_mut().enumerate() {
+        sub_chunk.chunk_index = format!("{chunk_index}-{sub_index}");
+        sub_chunk.chunk_start_line += start_line;
+        sub_chunk.chunk_end_line += start_line - 1;
+    }
+    Ok(Some(sub_chunks))
+}
+
+/// A node matched by a query, before it becomes one chunk or more.
+struct Item<'tree> {
+    node: tree_sitter::Node<'tree>,
+    function_name: Option<String>,
+    chunk_type: ChunkType,
+    attributes: Vec<String>,
+}
+
+/// Text of `node` with the bodies of its `children` elided, e.g. a class with the signatures
+/// of its methods only: the methods have their own chunks, whose parent is the class.
+fn skeleton(node: tree_sitter::Node, children: &[tree_sitter::Node], content: &str) -> String {
+    let mut bodies: Vec<tree_sitter::Node> = children
+        .iter()
+        .filter_map(|child| {
+            // Une définition décorée (Python) porte son corps dans `definition`
+            child
+                .child_by_field_name("body")
+                .or_else(|| child.child_by_field_name("definition")?.child_by_field_name("body"))
+        })
+        .collect();
+    bodies.sort_by_key(|body| body.start_byte());
+
+    let mut text = String::new();
+    let mut cursor = node.start_byte();
+    for body in bodies {
+        text.push_str(&content[cursor..body.start_byte()]);
+        text.push_str(if content[body.start_byte()..].starts_with('{') { "{ … }" } else { "…" });
+        cursor = body.end_byte();
+    }
+    text.push_str(&content[cursor..node.end_byte()]);
+    text
+}
+
+#[allow(clippy::too_many_arguments)]
 fn split_large_chunk(
     chunk_text: &str,
     file_path: &Path,
@@ -167,32 +589,138 @@ fn split_large_chunk(
     chunk_index: usize,
     function_name: &Option<String>,
     chunk_type: ChunkType,
+    attributes: &[String],
     splitter: &crate::chunking::TextSplitter,
     enc: &tiktoken_rs::CoreBPE,
 ) -> Result<Vec<Chunk>, String> {
     let mut sub_chunks =
         splitter.split_text(chunk_text, file_path.to_string_lossy().to_string(), enc)?;
-    let mut sub_index = 0;
-
-    for sub_chunk in &mut sub_chunks {
+    for (sub_index, sub_chunk) in sub_chunks.iter_mut().enumerate() {
         sub_chunk.chunk_index = format!("{chunk_index}-{sub_index}");
         sub_chunk.function_name = function_name.clone();
         sub_chunk.chunk_type = chunk_type.clone();
+        sub_chunk.attributes = attributes.to_vec();
         sub_chunk.chunk_start_line += start_line;
         sub_chunk.chunk_end_line += start_line;
-        sub_index += 1;
     }
 
     Ok(sub_chunks)
 }
 
+/// (kind, separator, name) of the `scopes` nodes enclosing `node`, outermost first.
+fn enclosing_scopes<'a>(
+    node: tree_sitter::Node,
+    scopes: &[(&'a str, &'a str)],
+    content: &str,
+) -> Vec<(&'a str, &'a str, String)> {
+    let mut enclosing = Vec::new();
+    let mut top_level = node;
+    let mut parent = node.parent();
+    while let Some(ancestor) = parent {
+        if let Some((kind, separator)) = scopes.iter().find(|(kind, _)| *kind == ancestor.kind()) {
+            // Un namespace anonyme ne qualifie pas le nom
+            if let Some(name) = scope_name(ancestor, content) {
+                enclosing.push((*kind, *separator, name));
+            }
+        }
+        if ancestor.parent().is_some() {
+            top_level = ancestor;
+        }
+        parent = ancestor.parent();
+    }
+    // `namespace Api;` (C#, PHP) n'a pas de corps : il s'applique aux déclarations qui le suivent
+    let mut sibling = top_level.prev_named_sibling();
+    while let Some(previous) = sibling {
+        let scope = scopes.iter().find(|(kind, _)| *kind == previous.kind());
+        if let (Some((kind, separator)), None) = (scope, previous.child_by_field_name("body")) {
+            if let Some(name) = previous.child_by_field_name("name") {
+                enclosing.push((*kind, *separator, content[name.start_byte()..name.end_byte()].to_string()));
+            }
+            break;
+        }
+        sibling = previous.prev_named_sibling();
+    }
+    enclosing.reverse();
+    enclosing
+}
+
+/// Name of a scope node, or the self type of a Rust impl without its generic parameters,
+/// `<Wrapper as fmt::Display>` for a trait impl so that the `fmt` of two traits differ.
+fn scope_name(node: tree_sitter::Node, content: &str) -> Option<String> {
+    let name = node.child_by_field_name("name").or_else(|| {
+        let self_type = node.child_by_field_name("type")?;
+        match self_type.kind() {
+            "generic_type" => self_type.child_by_field_name("type"),
+            _ => Some(self_type),
+        }
+    })?;
+    let name = &content[name.byte_range()];
+    Some(match node.child_by_field_name("trait") {
+        Some(implemented) => format!("<{name} as {}>", &content[implemented.byte_range()]),
+        None => name.to_string(),
+    })
+}
+
+/// C# attributes (`[HttpGet("{id}")]`) of `node` then of the `scopes` nodes enclosing it,
+/// e.g. the `[Authorize]` of a controller for its actions.
+fn attributes_of(node: tree_sitter::Node, scopes: &[(&str, &str)], content: &str) -> Vec<String> {
+    let mut attributes: Vec<String> = Vec::new();
+    let mut current = Some(node);
+    while let Some(declaration) = current {
+        if declaration == node || scopes.iter().any(|(kind, _)| *kind == declaration.kind()) {
+            let mut cursor = declaration.walk();
+            for list in declaration.children(&mut cursor).filter(|child| child.kind() == "attribute_list") {
+                let mut list_cursor = list.walk();
+                for attribute in list.named_children(&mut list_cursor).filter(|child| child.kind() == "attribute") {
+                    let text = content[attribute.start_byte()..attribute.end_byte()].to_string();
+                    if !attributes.contains(&text) {
+                        attributes.push(text);
+                    }
+                }
+            }
+        }
+        current = declaration.parent();
+    }
+    attributes
+}
+
 fn determine_chunk_type(query_str: &str) -> ChunkType {
-    if query_str.contains("class_declaration") {
+    // Capturée avec son type englobant (classe, receveur), ou membre Ruby : une méthode
+    if ["@parent", "@instance", "@singleton"].iter().any(|capture| query_str.contains(capture)) {
+        ChunkType::Method
+    } else if query_str.starts_with("(declaration ") {
+        ChunkType::Declaration
+    } else if query_str.contains("class_declaration")
+        || query_str.starts_with("(class ")
+        || query_str.contains("record_declaration")
+        || query_str.contains("class_definition")
+        || query_str.contains("class_specifier")
+    {
         ChunkType::Class
-    } else if query_str.contains("interface_declaration") {
+    } else if query_str.contains("interface_declaration") || query_str.contains("interface_type") {
         ChunkType::Interface
-    } else if query_str.contains("struct_item") {
+    } else if query_str.contains("const_declaration") || query_str.contains("var_declaration") {
+        ChunkType::Constant
+    } else if query_str.contains("struct_item")
+        || query_str.contains("struct_type")
+        || query_str.contains("struct_specifier")
+        || query_str.contains("struct_declaration")
+    {
         ChunkType::Struct
+    } else if query_str.starts_with("(module name:") || query_str.contains("mod_item") {
+        ChunkType::Module
+    } else if query_str.contains("trait_declaration") || query_str.contains("trait_item") {
+        ChunkType::Trait
+    } else if query_str.contains("enum_item") {
+        ChunkType::Enum
+    } else if query_str.contains("macro_definition") {
+        ChunkType::Macro
+    } else if query_str.contains("type_item") {
+        ChunkType::TypeAlias
+    } else if query_str.contains("const_item") || query_str.contains("static_item") {
+        ChunkType::Constant
+    } else if query_str.contains("property_declaration") {
+        ChunkType::Property
     } else if query_str.contains("impl_item") {
         ChunkType::Impl
     } else if query_str.contains("method_") {
@@ -202,21 +730,461 @@ fn determine_chunk_type(query_str: &str) -> ChunkType {
     }
 }
 
+/// Text of the `@name` capture, prefixed with the `@parent` one when the query has it,
+/// or qualified by the `@trait` it implements: `<name as Trait>`.
 fn extract_function_name(
     captures: &[tree_sitter::QueryCapture],
     query: &Query,
     content: &str,
 ) -> Option<String> {
-    captures
-        .iter()
-        .find(|c| query.capture_names()[c.index as usize] == "name")
-        .and_then(|c| {
-            let name_start = c.node.start_byte();
-            let name_end = c.node.end_byte();
-            if name_start < name_end && name_end <= content.len() {
-                Some(content[name_start..name_end].to_string())
-            } else {
-                None
-            }
-        })
+    let capture_text = |capture_name: &str| {
+        captures
+            .iter()
+            .find(|c| query.capture_names()[c.index as usize] == capture_name)
+            .and_then(|c| {
+                let name_start = c.node.start_byte();
+                let name_end = c.node.end_byte();
+                if name_start < name_end && name_end <= content.len() {
+                    Some(content[name_start..name_end].to_string())
+                } else {
+                    None
+                }
+            })
+    };
+    let name = capture_text("name")?;
+    match (capture_text("parent"), capture_text("trait")) {
+        (Some(parent), _) => Some(format!("{parent}.{name}")),
+        (None, Some(implemented)) => Some(format!("<{name} as {implemented}>")),
+        (None, None) => Some(name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tiktoken_rs::cl100k_base;
+
+    type Chunker = fn(&str, &Path, &chunking::TextSplitter, &tiktoken_rs::CoreBPE) -> Result<Vec<Chunk>, String>;
+
+    /// (type, name, first line) of every chunk of `content`.
+    fn chunk(
+        chunker: Chunker,
+        content: &str,
+        file: &str,
+    ) -> Vec<(String, String, usize)> {
+        let splitter = chunking::TextSplitter {
+            chunk_size: 350,
+            chunk_overlap: 100,
+        };
+        chunker(content, Path::new(file), &splitter, &cl100k_base().unwrap())
+            .unwrap()
+            .into_iter()
+            .map(|c| (format!("{:?}", c.chunk_type), c.function_name.unwrap_or_default(), c.chunk_start_line))
+            .collect()
+    }
+
+    #[test]
+    fn test_python_definitions() {
+        let content = r#"import functools
+
+@functools.cache
+def load(path):
+    """Read the config at path."""
+    return open(path).read()
+
+async def fetch(url):
+    return await get(url)
+
+class Client:
+    """HTTP client."""
+
+    def __init__(self, url):
+        self.url = url
+
+    @property
+    def host(self):
+        return self.url.split("/")[2]
+
+    class Retry:
+        async def wait(self):
+            pass
+
+if __name__ == "__main__":
+    print(load("config.toml"))
+"#;
+        let chunks = chunk(chunk_python, content, "client.py");
+        assert_eq!(
+            chunks,
+            vec![
+                ("Function".to_string(), "fetch".to_string(), 8),
+                ("Function".to_string(), "load".to_string(), 3),
+                ("Class".to_string(), "Client".to_string(), 11),
+                ("Method".to_string(), "Client.__init__".to_string(), 14),
+                ("Method".to_string(), "Retry.wait".to_string(), 22),
+                ("Method".to_string(), "Client.host".to_string(), 17),
+                ("LineChunk".to_string(), String::new(), 1),
+                ("LineChunk".to_string(), String::new(), 25),
+            ]
+        );
+
+        // Le code hors des définitions n'est pas perdu
+        let splitter = chunking::TextSplitter {
+            chunk_size: 350,
+            chunk_overlap: 100,
+        };
+        let chunks = chunk_python(content, Path::new("client.py"), &splitter, &cl100k_base().unwrap()).unwrap();
+        let main = chunks.last().unwrap();
+        assert_eq!(main.text, "if __name__ == \"__main__\":\n    print(load(\"config.toml\"))");
+        assert_eq!((main.chunk_start_line, main.chunk_end_line), (25, 26));
+    }
+
+    #[test]
+    fn test_go_methods_have_their_receiver_as_parent() {
+        let content = r#"package server
+
+const (
+	DefaultPort = 8080
+	DefaultHost = "localhost"
+)
+
+var ErrClosed = errors.New("closed")
+
+type Server struct {
+	addr string
+}
+
+type Handler interface {
+	Serve(req *Request) error
+}
+
+type (
+	Request struct{ path string }
+	Closer  interface{ Close() error }
+)
+
+func New(addr string) *Server {
+	return &Server{addr: addr}
+}
+
+func (s *Server) Start() error {
+	return listen(s.addr)
+}
+
+func (c Cache[K]) Get(key K) string {
+	return ""
+}
+"#;
+        let chunks = chunk(chunk_go, content, "server.go");
+        assert_eq!(
+            chunks,
+            vec![
+                ("Function".to_string(), "New".to_string(), 23),
+                ("Method".to_string(), "Server.Start".to_string(), 27),
+                ("Method".to_string(), "Cache.Get".to_string(), 31),
+                ("Struct".to_string(), "Server".to_string(), 10),
+                ("Struct".to_string(), "Request".to_string(), 19),
+                ("Interface".to_string(), "Handler".to_string(), 14),
+                ("Interface".to_string(), "Closer".to_string(), 20),
+                ("Constant".to_string(), "DefaultPort".to_string(), 4),
+                ("Constant".to_string(), "DefaultHost".to_string(), 5),
+                ("Constant".to_string(), "ErrClosed".to_string(), 8),
+                ("LineChunk".to_string(), String::new(), 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cpp_scopes_and_header_declarations() {
+        let header = r#"#pragma once
+namespace net {
+
+int parse_port(const char *text);
+Server *make_server();
+
+class Server {
+public:
+    void start();
+    int port() const { return port_; }
+private:
+    int port_;
+};
+
+}
+"#;
+        let chunks = chunk(chunk_cpp, header, "include/server.hpp");
+        assert_eq!(
+            chunks,
+            vec![
+                ("Method".to_string(), "net::Server::port".to_string(), 10),
+                ("Class".to_string(), "net::Server".to_string(), 7),
+                ("Declaration".to_string(), "net::parse_port".to_string(), 4),
+                ("Declaration".to_string(), "net::make_server".to_string(), 5),
+                ("LineChunk".to_string(), String::new(), 1),
+            ]
+        );
+
+        let source = r#"#include "server.hpp"
+namespace net {
+void Server::start() {
+    listen(port_);
+}
+}
+
+static Config &config() {
+    static Config c;
+    return c;
+}
+"#;
+        let chunks = chunk(chunk_cpp, source, "src/server.cc");
+        assert_eq!(
+            chunks,
+            vec![
+                ("Method".to_string(), "net::Server::start".to_string(), 3),
+                ("Function".to_string(), "config".to_string(), 8),
+                ("LineChunk".to_string(), String::new(), 1),
+            ]
+        );
+
+        let c = "typedef struct {\n    int fd;\n} conn_t;\n\nstruct buffer { char *data; };\n\nint conn_close(conn_t *c) {\n    return close(c->fd);\n}\n";
+        assert_eq!(
+            chunk(chunk_c, c, "conn.c"),
+            vec![
+                ("Function".to_string(), "conn_close".to_string(), 7),
+                ("Struct".to_string(), "buffer".to_string(), 5),
+                ("Struct".to_string(), "conn_t".to_string(), 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_csharp_controller_keeps_its_attributes() {
+        let source = r#"using Microsoft.AspNetCore.Mvc;
+
+namespace Api.Controllers;
+
+[ApiController]
+[Authorize(Roles = "Admin")]
+public class UsersController : ControllerBase
+{
+    public UsersController(IUserService users) { _users = users; }
+
+    public int Count { get; set; }
+
+    [HttpGet("{id}"), AllowAnonymous]
+    public IActionResult Get(int id) => Ok(_users.Find(id));
+}
+
+public record UserDto(int Id, string Name);
+"#;
+        let splitter = chunking::TextSplitter {
+            chunk_size: 350,
+            chunk_overlap: 100,
+        };
+        let chunks = chunk_csharp(source, Path::new("Controllers/UsersController.cs"), &splitter, &cl100k_base().unwrap()).unwrap();
+        let summary: Vec<_> = chunks
+            .iter()
+            .map(|c| (format!("{:?}", c.chunk_type), c.function_name.clone().unwrap_or_default(), c.chunk_start_line))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("Class".to_string(), "Api.Controllers.UsersController".to_string(), 5),
+                ("Class".to_string(), "Api.Controllers.UserDto".to_string(), 17),
+                ("Method".to_string(), "Api.Controllers.UsersController.Get".to_string(), 13),
+                ("Method".to_string(), "Api.Controllers.UsersController.UsersController".to_string(), 9),
+                ("Property".to_string(), "Api.Controllers.UsersController.Count".to_string(), 11),
+                ("LineChunk".to_string(), String::new(), 1),
+            ]
+        );
+
+        let get = &chunks[2];
+        assert!(get.text.starts_with("[HttpGet(\"{id}\"), AllowAnonymous]"));
+        assert_eq!(
+            get.attributes,
+            vec!["HttpGet(\"{id}\")", "AllowAnonymous", "ApiController", "Authorize(Roles = \"Admin\")"]
+        );
+        assert!(chunks[1].attributes.is_empty());
+    }
+
+    #[test]
+    fn test_ruby_methods_are_named_after_their_class() {
+        let source = r#"module Admin
+  class UsersController < ApplicationController
+    def index
+      @users = User.all
+    end
+
+    def self.policy
+      :admin
+    end
+
+    class << self
+      def routes; end
+    end
+  end
+
+  module Auditable
+    included do
+      def audit; end
+    end
+  end
+end
+
+def helper; end
+"#;
+        assert_eq!(
+            chunk(chunk_ruby, source, "app/controllers/admin/users_controller.rb"),
+            vec![
+                ("Module".to_string(), "Admin".to_string(), 1),
+                ("Module".to_string(), "Admin::Auditable".to_string(), 16),
+                ("Class".to_string(), "Admin::UsersController".to_string(), 2),
+                ("Method".to_string(), "Admin::UsersController.policy".to_string(), 7),
+                ("Method".to_string(), "Admin::UsersController.routes".to_string(), 12),
+                ("Method".to_string(), "Admin::UsersController#index".to_string(), 3),
+                ("Method".to_string(), "Admin::Auditable#audit".to_string(), 18),
+                ("Function".to_string(), "helper".to_string(), 23),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_php_methods_are_named_after_their_namespace_and_class() {
+        let source = r#"<?php
+
+namespace App\Http\Controllers;
+
+trait Paginates
+{
+    protected function perPage(): int { return 20; }
+}
+
+class UserController extends Controller
+{
+    use Paginates;
+
+    public static function routes(): array { return []; }
+
+    public function store(Request $request)
+    {
+        return User::create($request->all());
+    }
+}
+
+function helper() { return 1; }
+"#;
+        assert_eq!(
+            chunk(chunk_php, source, "app/Http/Controllers/UserController.php"),
+            vec![
+                ("Class".to_string(), "App\\Http\\Controllers\\UserController".to_string(), 10),
+                ("Trait".to_string(), "App\\Http\\Controllers\\Paginates".to_string(), 5),
+                ("Method".to_string(), "App\\Http\\Controllers\\Paginates::perPage".to_string(), 7),
+                ("Method".to_string(), "App\\Http\\Controllers\\UserController::routes".to_string(), 14),
+                ("Method".to_string(), "App\\Http\\Controllers\\UserController::store".to_string(), 16),
+                ("Function".to_string(), "App\\Http\\Controllers\\helper".to_string(), 22),
+                ("LineChunk".to_string(), String::new(), 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rust_items() {
+        let source = r#"pub const LIMIT: usize = 10;
+
+pub type Result<T> = std::result::Result<T, String>;
+
+pub struct Wrapper<T>(T);
+
+pub enum Mode { Full, Incremental }
+
+pub trait Shape {
+    fn area(&self) -> f64 { 0.0 }
+}
+
+impl<T> Wrapper<T> {
+    const EMPTY: usize = 0;
+
+    fn new(value: T) -> Self { Wrapper(value) }
+}
+
+impl<T: fmt::Display> fmt::Display for Wrapper<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { self.0.fmt(f) }
+}
+
+macro_rules! square {
+    ($x:expr) => { $x * $x };
+}
+
+mod helpers {
+    pub fn clamp(value: usize) -> usize {
+        const MAX: usize = 100;
+        value.min(MAX)
+    }
+}
+
+impl fmt::Debug for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { Ok(()) }
+}
+"#;
+        assert_eq!(
+            chunk(chunk_rust, source, "src/lib.rs"),
+            vec![
+                ("Method".to_string(), "Shape::area".to_string(), 10),
+                ("Method".to_string(), "Wrapper::new".to_string(), 16),
+                ("Method".to_string(), "<Wrapper as fmt::Display>::fmt".to_string(), 20),
+                ("Function".to_string(), "helpers::clamp".to_string(), 28),
+                ("Method".to_string(), "<Mode as fmt::Debug>::fmt".to_string(), 35),
+                ("Struct".to_string(), "Wrapper".to_string(), 5),
+                ("Enum".to_string(), "Mode".to_string(), 7),
+                ("Trait".to_string(), "Shape".to_string(), 9),
+                ("Impl".to_string(), "<Mode as fmt::Debug>".to_string(), 34),
+                ("Impl".to_string(), "<Wrapper as fmt::Display>".to_string(), 19),
+                ("Impl".to_string(), "Wrapper".to_string(), 13),
+                ("Module".to_string(), "helpers".to_string(), 27),
+                ("Macro".to_string(), "square".to_string(), 23),
+                ("TypeAlias".to_string(), "Result".to_string(), 3),
+                ("Constant".to_string(), "LIMIT".to_string(), 1),
+                ("Constant".to_string(), "Wrapper::EMPTY".to_string(), 14),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_class_is_a_skeleton_of_its_methods() {
+        let source = r#"class Cache {
+    private Map<String, String> entries;
+
+    String get(String key) {
+        return entries.get(key);
+    }
+
+    void clear() { entries.clear(); }
+}
+"#;
+        let splitter = chunking::TextSplitter {
+            chunk_size: 350,
+            chunk_overlap: 100,
+        };
+        let chunks = chunk_java(source, Path::new("Cache.java"), &splitter, &cl100k_base().unwrap()).unwrap();
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(
+            chunks[0].text,
+            "class Cache {\n    private Map<String, String> entries;\n\n    String get(String key) { … }\n\n    void clear() { … }\n}"
+        );
+        assert!(chunks[0].parent_id.is_empty());
+        assert_eq!(chunks[1].text, "String get(String key) {\n        return entries.get(key);\n    }");
+        assert_eq!(chunks[1].parent_id, chunks[0].chunk_index);
+        assert_eq!(chunks[2].parent_id, chunks[0].chunk_index);
+
+        // Un squelette trop long est découpé comme un autre chunk, ses méthodes renvoient à sa première partie
+        let fields: String = (0..500).map(|i| format!("    private int field{i} = {i};\n")).collect();
+        let source = format!("class Large {{\n{fields}\n    void clear() {{ entries.clear(); }}\n}}\n");
+        assert!(source.len() > crate::utils::MAX_SEQUENCE_LENGTH);
+        let mut chunks = chunk_java(&source, Path::new("Large.java"), &splitter, &cl100k_base().unwrap()).unwrap();
+        assert!(chunks.iter().all(|c| crate::utils::is_embeddable(&c.text)));
+        assert!(chunks.iter().filter(|c| c.chunk_index.starts_with("0-")).count() > 1);
+        crate::utils::assign_point_ids(&mut chunks);
+        let method = chunks.iter().find(|c| matches!(c.chunk_type, ChunkType::Method)).unwrap();
+        assert_eq!(method.parent_id, chunks[0].point_id);
+        assert_eq!(chunks[0].chunk_index, "0-0");
+    }
 }
@@ -0,0 +1,215 @@
+use crate::types::ScanReport;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use uuid::Uuid;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ScanPhase {
+    Queued,
+    Parsing,
+    Chunking,
+    Embedding,
+    Upserting,
+    Done,
+    /// Finished, but some embedding batches failed and were not indexed.
+    Partial,
+    Failed,
+    Cancelled,
+}
+
+impl ScanPhase {
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            ScanPhase::Done | ScanPhase::Partial | ScanPhase::Failed | ScanPhase::Cancelled
+        )
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct JobStatus {
+    pub id: String,
+    pub repo: String,
+    pub phase: ScanPhase,
+    pub files_done: usize,
+    pub files_total: usize,
+    pub chunks_done: usize,
+    pub chunks_total: usize,
+    pub errors: Vec<String>,
+    pub started_at: u64,          // unix timestamp (s)
+    pub finished_at: Option<u64>, // unix timestamp (s)
+    pub report: Option<ScanReport>,
+}
+
+/// Shared handle used by `scan_repo` to report its progress and to check for cancellation.
+#[derive(Clone)]
+pub struct ScanProgress {
+    status: Arc<Mutex<JobStatus>>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl ScanProgress {
+    pub fn new(repo: &str) -> Self {
+        Self {
+            status: Arc::new(Mutex::new(JobStatus {
+                id: Uuid::new_v4().to_string(),
+                repo: repo.to_string(),
+                phase: ScanPhase::Queued,
+                files_done: 0,
+                files_total: 0,
+                chunks_done: 0,
+                chunks_total: 0,
+                errors: Vec::new(),
+                started_at: now(),
+                finished_at: None,
+                report: None,
+            })),
+            cancelled: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn id(&self) -> String {
+        self.status.lock().unwrap().id.clone()
+    }
+
+    pub fn snapshot(&self) -> JobStatus {
+        self.status.lock().unwrap().clone()
+    }
+
+    pub fn set_phase(&self, phase: ScanPhase) {
+        let mut status = self.status.lock().unwrap();
+        status.phase = phase;
+        if phase.is_finished() {
+            status.finished_at = Some(now());
+        }
+    }
+
+    /// Attach the final report; the job ends as cancelled if a cancel was requested,
+    /// or as partial if some batches failed.
+    pub fn finish(&self, report: ScanReport) {
+        let partial = !report.failed_batches.is_empty();
+        self.status.lock().unwrap().report = Some(report);
+        if self.is_cancelled() {
+            self.set_phase(ScanPhase::Cancelled);
+        } else if partial {
+            self.set_phase(ScanPhase::Partial);
+        } else {
+            self.set_phase(ScanPhase::Done);
+        }
+    }
+
+    pub fn set_files_total(&self, total: usize) {
+        self.status.lock().unwrap().files_total = total;
+    }
+
+    pub fn file_done(&self) {
+        self.status.lock().unwrap().files_done += 1;
+    }
+
+    pub fn set_chunks_total(&self, total: usize) {
+        self.status.lock().unwrap().chunks_total = total;
+    }
+
+    pub fn chunks_done(&self, count: usize) {
+        self.status.lock().unwrap().chunks_done += count;
+    }
+
+    pub fn error(&self, message: String) {
+        eprintln!("{message}");
+        self.status.lock().unwrap().errors.push(message);
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+/// In-memory list of the scan jobs started since the API booted.
+#[derive(Clone, Default)]
+pub struct JobRegistry {
+    jobs: Arc<Mutex<HashMap<String, ScanProgress>>>,
+}
+
+impl JobRegistry {
+    /// Register a new job, unless a scan of the same repo is still running.
+    pub fn start(&self, repo: &str) -> Result<ScanProgress, String> {
+        let mut jobs = self.jobs.lock().unwrap();
+        if let Some(running) = jobs.values().map(|j| j.snapshot()).find(|s| {
+            s.repo == repo && !s.phase.is_finished()
+        }) {
+            return Err(format!("A scan of {repo} is already running (job {})", running.id));
+        }
+
+        let progress = ScanProgress::new(repo);
+        jobs.insert(progress.id(), progress.clone());
+        Ok(progress)
+    }
+
+    pub fn get(&self, id: &str) -> Option<ScanProgress> {
+        self.jobs.lock().unwrap().get(id).cloned()
+    }
+
+    pub fn list(&self) -> Vec<JobStatus> {
+        let mut jobs: Vec<_> = self
+            .jobs
+            .lock()
+            .unwrap()
+            .values()
+            .map(|j| j.snapshot())
+            .collect();
+        jobs.sort_by_key(|j| std::cmp::Reverse(j.started_at));
+        jobs
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_rejects_concurrent_scan_of_same_repo() {
+        let registry = JobRegistry::default();
+        let job = registry.start("repo").unwrap();
+        assert!(registry.start("repo").is_err());
+        assert!(registry.start("other").is_ok());
+
+        job.set_phase(ScanPhase::Done);
+        assert!(job.snapshot().finished_at.is_some());
+        assert!(registry.start("repo").is_ok());
+        assert_eq!(registry.list().len(), 3);
+    }
+
+    #[test]
+    fn test_failed_batches_end_as_partial() {
+        let job = ScanProgress::new("repo");
+        job.finish(ScanReport::default());
+        assert_eq!(job.snapshot().phase, ScanPhase::Done);
+
+        let job = ScanProgress::new("repo");
+        job.finish(ScanReport {
+            failed_batches: vec![crate::types::FailedBatch {
+                index: 0,
+                chunks: 10,
+                error: "timeout".into(),
+            }],
+            ..Default::default()
+        });
+        assert_eq!(job.snapshot().phase, ScanPhase::Partial);
+        assert!(job.snapshot().phase.is_finished());
+    }
+}
@@ -1,122 +1,746 @@
-use std::{fs, path::Path};
+use std::{collections::BTreeMap, fs, path::Path, time::Instant};
 
-use crate::{vector_store::VectorStore};
+use crate::{checkpoint::Checkpoint, vector_store::VectorStore};
+use futures_util::StreamExt;
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 
 mod api;
 mod chatter;
+mod checkpoint;
 mod chunk_writter;
 mod chunking;
 mod config;
+mod doc_chunking;
 mod embedding;
+mod git;
 mod intelligent_chunking;
+mod jobs;
+mod manifest;
 mod parsing;
+mod rate_limit;
+mod redaction;
+mod repo_config;
 mod types;
 mod utils;
 mod vector_store;
+mod watch;
+mod workspace;
 
 pub use api::*;
+use std::collections::{HashMap, HashSet};
 use tokio::sync::mpsc::Sender;
-use types::{Chunk, FilterConfig};
+use types::{Chunk, FailedBatch, FilterConfig, SkippedChunk, SkippedFile};
+pub use types::{ChunkScope, ScanEstimate, ScanMode, ScanReport};
+pub use jobs::{JobRegistry, JobStatus, ScanPhase, ScanProgress};
+pub use watch::watch_repo;
+pub use workspace::Workspace;
 
-pub async fn scan_repo(repo_name: String, filter_config: Option<FilterConfig>) -> String {
+/// Index `clone/<repo_name>`: the checked out files, or the tree of `git_ref`
+/// (branch, tag or commit) read from the git object database into its own collection.
+pub async fn scan_repo(
+    repo_name: String,
+    git_ref: Option<String>,
+    filter_config: Option<FilterConfig>,
+    mode: ScanMode,
+    progress: &ScanProgress,
+) -> Result<ScanReport, String> {
+    let started = Instant::now();
+    let mut report = ScanReport {
+        repo: repo_name.clone(),
+        collection: git::collection_name(&repo_name, git_ref.as_deref()),
+        git_ref: git_ref.clone(),
+        ..Default::default()
+    };
+
+    let result = run_scan(&repo_name, git_ref.as_deref(), filter_config, mode, progress, &mut report).await;
+    report.duration_secs = started.elapsed().as_secs_f64();
+
+    match result {
+        Ok(()) => {
+            println!("{}: {}", report.collection, report.summary());
+            progress.finish(report.clone());
+            Ok(report)
+        }
+        Err(e) => {
+            progress.error(e.clone());
+            progress.set_phase(ScanPhase::Failed);
+            Err(e)
+        }
+    }
+}
+
+async fn run_scan(
+    repo_name: &str,
+    git_ref: Option<&str>,
+    filter_config: Option<FilterConfig>,
+    mode: ScanMode,
+    progress: &ScanProgress,
+    report: &mut ScanReport,
+) -> Result<(), String> {
     let config = config::Config::from_env();
-    
+    let repo_dir = format!("clone/{repo_name}");
+    let collection = report.collection.clone();
+
     println!("Start parsing repo");
-    let meta_files = parsing::parse_repo(format!("clone/{repo_name}"), filter_config);
-    println!("{} files detected.\nStart chunking", meta_files.len());
+    progress.set_phase(ScanPhase::Parsing);
+    let settings = repo_config::ScanSettings::resolve(&repo_dir, git_ref, filter_config)?;
+    let (meta_files, skipped_files, commit) = parse_files(repo_dir, git_ref, &settings)?;
+    report.commit = commit;
+    println!("{} files detected.", meta_files.len());
+    report.files_seen = meta_files.len() + skipped_files.len();
+    report.files_skipped = skipped_files;
 
-    let splitter = chunking::TextSplitter {
-        chunk_size: config.chunk_size,
-        chunk_overlap: config.chunk_overlap,
-    };
+    if progress.is_cancelled() {
+        return Ok(());
+    }
+
+    let mapped_languages = parsing::mapped_languages(&meta_files);
+    let chunk_file = format!("generated/{collection}");
+    let checkpoint_file = Checkpoint::path(&collection);
+    let fingerprint = checkpoint::fingerprint(&meta_files, mode, &settings);
+
+    // Un checkpoint du même scan existe : on reprend sur le fichier de chunks déjà généré
+    let resumed = Checkpoint::load(&checkpoint_file)
+        .filter(|c| c.fingerprint == fingerprint && Path::new(&chunk_file).exists());
+
+    // Un scan repris a déjà passé le contrôle du manifest, avec la même config (cf. fingerprint)
+    let mut db = match (mode, &resumed) {
+        (ScanMode::Full, None) => {
+            VectorStore::reset_or_create(&collection, config.vector_dimension).await
+        }
+        _ => VectorStore::open_or_create(&collection, config.vector_dimension).await,
+    }
+    .map_err(|e| format!("Error opening vector store: {e}"))?;
+    if mode == ScanMode::Full && resumed.is_none() {
+        manifest::IndexManifest::remove(&collection);
+    }
 
-    let writter =
-        chunk_writter::ChunkBinWriter::create(format!("generated/{}", &repo_name).as_str())
-            .unwrap();
-    meta_files.par_iter().for_each(|meta| {
-        let path = Path::new(&meta.path);
-        match splitter.split_file(path) {
-            Ok(chunks) => {
-                for chunk in chunks {
-                    writter.write(&utils::prepare_chunk(chunk)).unwrap();
+    // Fichiers retirés de l'index par un scan incrémental, dont les liens sont à défaire
+    let mut removed = Vec::new();
+    if let Some(checkpoint) = &resumed {
+        println!(
+            "Resuming scan of {collection}: {}/{} batches already stored",
+            checkpoint.committed_batches.len(),
+            checkpoint.total_batches
+        );
+        progress.set_files_total(meta_files.len());
+        report.files_indexed = meta_files.len();
+    } else {
+        let meta_files = match mode {
+            ScanMode::Full => meta_files,
+            ScanMode::Incremental => {
+                let indexed = db
+                    .indexed_file_hashes(None)
+                    .await
+                    .map_err(|e| format!("Error reading indexed files: {e}"))?;
+                match indexed {
+                    // Ses points ne seraient pas remplacés mais dupliqués : on repart de zéro
+                    None => {
+                        println!("Index of {collection} predates incremental scans, running a full scan");
+                        db = VectorStore::reset_or_create(&collection, config.vector_dimension)
+                            .await
+                            .map_err(|e| format!("Error opening vector store: {e}"))?;
+                        manifest::IndexManifest::remove(&collection);
+                        meta_files
+                    }
+                    Some(indexed) => {
+                        // Ajouter des points à un index construit avec une autre config le rendrait incohérent
+                        manifest::check_update(&collection, &settings, !indexed.is_empty())?;
+                        let (mut to_index, to_delete) = parsing::diff_with_index(meta_files.clone(), &indexed);
+                        println!(
+                            "{} files to index, {} files to remove from the index",
+                            to_index.len(),
+                            to_delete.len()
+                        );
+                        let orphans = db
+                            .delete_files(&to_delete)
+                            .await
+                            .map_err(|e| format!("Error deleting stale vectors: {e}"))?;
+                        // Les copies d'un chunk supprimé n'ont plus de point : on les ré-indexe
+                        to_index.extend(meta_files.into_iter().filter(|m| orphans.contains(&m.path)));
+                        removed = to_delete;
+                        to_index
+                    }
                 }
             }
-            Err(e) => eprintln!("Erreur sur {:?} : {}", meta.path, e),
+        };
+
+        progress.set_files_total(meta_files.len());
+        if meta_files.is_empty() {
+            println!("Index of {collection} is already up to date");
+            link_to_index(&db, &mut Vec::new(), &removed).await?;
+            return manifest::IndexManifest::current(&collection, &settings, report.commit.clone()).save();
         }
-    });
-    writter.flush().unwrap();
-    println!("Finished preparing chunks");
-
-    let reader =
-        chunk_writter::ChunkBinReader::<Chunk>::open(format!("generated/{}", &repo_name).as_str())
-            .unwrap();
-    let all_chunks: Vec<_> = reader
-        .map(|r| match r {
-            Ok(chunk) => {
-                println!("{}: {}", chunk.path, chunk.chunk_index);
-                chunk
-            }
-            Err(e) => {
-                panic!("Error deconding chunks : {e}")
-            }
-        })
-        .collect();
 
-    utils::calculate_cost(&all_chunks);
+        println!("Start chunking");
+        progress.set_phase(ScanPhase::Chunking);
+
+        let splitter = settings.splitter();
+        let redactor = settings.redactor()?;
+
+        let writter = chunk_writter::ChunkBinWriter::create(&chunk_file)
+            .map_err(|e| format!("Error creating {chunk_file}: {e}"))?;
+        let failed_files: Vec<SkippedFile> = meta_files
+            .par_iter()
+            .filter_map(|meta| {
+                if progress.is_cancelled() {
+                    return None;
+                }
+                let failure = match split_meta_file(&splitter, &redactor, meta) {
+                    Ok(chunks) => chunks
+                        .iter()
+                        .find_map(|chunk| writter.write(chunk).err())
+                        .map(|e| format!("unable to write chunks: {e}")),
+                    Err(reason) => Some(reason),
+                };
+                progress.file_done();
+                failure.map(|reason| SkippedFile {
+                    path: meta.path.clone(),
+                    reason,
+                })
+            })
+            .collect();
+        writter
+            .flush()
+            .map_err(|e| format!("Error writing {chunk_file}: {e}"))?;
+        println!("Finished preparing chunks");
+
+        report.secrets_redacted = redactor.counts();
+        report.files_indexed = meta_files.len() - failed_files.len();
+        report.files_skipped.extend(failed_files);
+
+        if progress.is_cancelled() {
+            return Ok(());
+        }
+    }
+
+    let reader = chunk_writter::ChunkBinReader::<Chunk>::open(&chunk_file)
+        .map_err(|e| format!("Error opening {chunk_file}: {e}"))?;
+    let all_chunks = reader
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Error decoding chunks of {chunk_file}: {e}"))?;
+    let (mut all_chunks, deduplicated) = utils::dedupe_chunks(all_chunks);
+    report.chunks_deduplicated = deduplicated;
+    match mode {
+        ScanMode::Full => utils::link_declarations(&mut all_chunks),
+        ScanMode::Incremental => link_to_index(&db, &mut all_chunks, &removed).await?,
+    }
+
+    count_chunks(
+        &all_chunks,
+        &mapped_languages,
+        &mut report.chunks_per_language,
+        &mut report.chunks_per_type,
+    );
+
+    report.estimated_tokens = utils::calculate_cost(&all_chunks);
 
     println!("Start embedding");
 
-    let embedder = embedding::create_embedder();
+    progress.set_chunks_total(all_chunks.len());
     let batches = utils::make_batches(all_chunks);
 
-    let db = VectorStore::reset_or_create(&repo_name, config.vector_dimension)
+    let mut checkpoint = resumed
+        .filter(|c| c.total_batches == batches.len())
+        .unwrap_or_else(|| Checkpoint::new(fingerprint, batches.len()));
+    checkpoint
+        .save(&checkpoint_file)
+        .map_err(|e| format!("Error writing checkpoint: {e}"))?;
+
+    let mut pending = Vec::new();
+    for (index, batch) in batches.into_iter().enumerate() {
+        if checkpoint.is_committed(index) {
+            progress.chunks_done(batch.len());
+        } else {
+            pending.push((index, batch));
+        }
+    }
+
+    embed_and_store(&db, pending, progress, report, |index| {
+        checkpoint.commit(index);
+        checkpoint
+            .save(&checkpoint_file)
+            .map_err(|e| format!("Error writing checkpoint: {e}"))
+    })
+    .await?;
+    if progress.is_cancelled() {
+        return Ok(());
+    }
+
+    if checkpoint.is_complete() {
+        Checkpoint::remove(&checkpoint_file);
+        // Écrit seulement maintenant : le manifest décrit un index complet
+        manifest::IndexManifest::current(&collection, &settings, report.commit.clone()).save()?;
+        println!("Embedding of {collection} finished with sucess !");
+    } else {
+        println!(
+            "Embedding of {collection} finished with {} failed batches, scan again to retry them",
+            checkpoint.total_batches - checkpoint.committed_batches.len()
+        );
+    }
+    Ok(())
+}
+
+/// Re-index only the `touched` files of the working tree of `clone/<repo_name>` (paths relative
+/// to the repo root), e.g. after a filesystem change: points of deleted, ignored or changed files
+/// are dropped, added or changed files are chunked and embedded again.
+pub async fn update_files(
+    repo_name: &str,
+    touched: &[String],
+    filter_config: Option<FilterConfig>,
+    progress: &ScanProgress,
+) -> Result<ScanReport, String> {
+    let started = Instant::now();
+    let mut report = ScanReport {
+        repo: repo_name.to_string(),
+        collection: git::collection_name(repo_name, None),
+        ..Default::default()
+    };
+
+    let result = run_update(repo_name, touched, filter_config, progress, &mut report).await;
+    report.duration_secs = started.elapsed().as_secs_f64();
+
+    match result {
+        Ok(()) => {
+            println!("{}: {}", report.collection, report.summary());
+            progress.finish(report.clone());
+            Ok(report)
+        }
+        Err(e) => {
+            progress.error(e.clone());
+            progress.set_phase(ScanPhase::Failed);
+            Err(e)
+        }
+    }
+}
+
+async fn run_update(
+    repo_name: &str,
+    touched: &[String],
+    filter_config: Option<FilterConfig>,
+    progress: &ScanProgress,
+    report: &mut ScanReport,
+) -> Result<(), String> {
+    let config = config::Config::from_env();
+    let repo_dir = format!("clone/{repo_name}");
+
+    progress.set_phase(ScanPhase::Parsing);
+    let settings = repo_config::ScanSettings::resolve(&repo_dir, None, filter_config)?;
+    let (meta_files, skipped_files, mut to_delete) =
+        parsing::parse_touched(repo_dir.clone(), touched, &settings.parse_options()?);
+    report.commit = git::head_commit(&repo_dir);
+    report.files_seen = touched.len();
+    report.files_skipped = skipped_files;
+
+    let db = VectorStore::open_or_create(&report.collection, config.vector_dimension)
         .await
-        .expect("Erreur init vectorstore");
-
-    for batch in batches {
-        match embedder.embed_batch(batch).await {
-            Ok(embs) => match db.insert_many_embeddings_bulk(&embs).await {
-                Ok(_) => (),
-                Err(_) => eprintln!("Error saving vectors in db"),
-            },
-            Err(e) => {
-                panic!("{e}");
+        .map_err(|e| format!("Error opening vector store: {e}"))?;
+    let has_points = !db
+        .is_empty()
+        .await
+        .map_err(|e| format!("Error reading {}: {e}", report.collection))?;
+    manifest::check_update(&report.collection, &settings, has_points)?;
+
+    // Un fichier sauvegardé sans changement de contenu n'est pas ré-embeddé
+    let paths: Vec<String> = meta_files.iter().map(|m| m.path.clone()).collect();
+    let indexed = db
+        .indexed_file_hashes(Some(&paths))
+        .await
+        .map_err(|e| format!("Error reading indexed files: {e}"))?
+        .ok_or_else(|| format!("Index of {} predates incremental scans, run a full scan first", report.collection))?;
+    let (mut meta_files, changed) = parsing::diff_with_index(meta_files, &indexed);
+    to_delete.extend(changed);
+
+    println!(
+        "{}: {} files to index, {} files to remove from the index",
+        report.collection,
+        meta_files.len(),
+        to_delete.len()
+    );
+    let orphans = db
+        .delete_files(&to_delete)
+        .await
+        .map_err(|e| format!("Error deleting stale vectors: {e}"))?;
+    // Les copies d'un chunk supprimé n'ont plus de point : on les ré-indexe
+    let orphans: Vec<String> = orphans
+        .iter()
+        .filter_map(|path| path.strip_prefix(&format!("{repo_dir}/")).map(str::to_string))
+        .collect();
+    meta_files.extend(parsing::parse_touched(repo_dir, &orphans, &settings.parse_options()?).0);
+
+    progress.set_files_total(meta_files.len());
+    let manifest = manifest::IndexManifest::current(&report.collection, &settings, report.commit.clone());
+    if meta_files.is_empty() {
+        link_to_index(&db, &mut Vec::new(), &to_delete).await?;
+        return manifest.save();
+    }
+
+    progress.set_phase(ScanPhase::Chunking);
+    let redactor = settings.redactor()?;
+    let (chunks, failed_files) = chunk_files(&settings.splitter(), &redactor, &meta_files, Some(progress));
+    let (mut chunks, deduplicated) = utils::dedupe_chunks(chunks);
+    report.chunks_deduplicated = deduplicated;
+    link_to_index(&db, &mut chunks, &to_delete).await?;
+    report.secrets_redacted = redactor.counts();
+    report.files_indexed = meta_files.len() - failed_files.len();
+    report.files_skipped.extend(failed_files);
+
+    count_chunks(
+        &chunks,
+        &parsing::mapped_languages(&meta_files),
+        &mut report.chunks_per_language,
+        &mut report.chunks_per_type,
+    );
+    report.estimated_tokens = utils::count_tokens(&chunks);
+    progress.set_chunks_total(chunks.len());
+
+    let batches = utils::make_batches(chunks).into_iter().enumerate().collect();
+    embed_and_store(&db, batches, progress, report, |_| Ok(())).await?;
+    if progress.is_cancelled() || !report.failed_batches.is_empty() {
+        return Ok(());
+    }
+    manifest.save()
+}
+
+/// Embed `batches` (tagged with their index) `EMBEDDING_CONCURRENCY` at a time and upsert
+/// them. `on_stored` is called with the index of every batch stored in Qdrant. Failed batches
+/// are recorded in the report; returns early, without error, when the scan is cancelled.
+async fn embed_and_store(
+    db: &VectorStore,
+    batches: Vec<(usize, Vec<Chunk>)>,
+    progress: &ScanProgress,
+    report: &mut ScanReport,
+    mut on_stored: impl FnMut(usize) -> Result<(), String>,
+) -> Result<(), String> {
+    let config = config::Config::from_env();
+    let embedder = embedding::create_embedder();
+
+    // Plusieurs batches en vol ; les upserts et le checkpoint restent séquentiels
+    progress.set_phase(ScanPhase::Embedding);
+    let embedder = &*embedder;
+    let mut embedded_batches = futures_util::stream::iter(batches)
+        .map(|(index, batch)| async move {
+            let batch_len = batch.len();
+            (index, batch_len, embedder.embed_batch(batch).await)
+        })
+        .buffer_unordered(config.embedding_concurrency);
+
+    while let Some((index, batch_len, result)) = embedded_batches.next().await {
+        if progress.is_cancelled() {
+            return Ok(());
+        }
+        let embedded = match result {
+            Ok(embedded) => embedded,
+            Err(error) => {
+                progress.error(format!("Error embedding batch {index}: {error}"));
+                report.failed_batches.push(FailedBatch {
+                    index,
+                    chunks: batch_len,
+                    error,
+                });
+                continue;
+            }
+        };
+
+        report.actual_tokens += embedded.tokens;
+        report
+            .chunks_skipped
+            .extend(embedded.skipped.iter().map(|c| SkippedChunk {
+                path: c.path.clone(),
+                chunk_index: c.chunk_index.clone(),
+                reason: format!("empty or longer than {} bytes", utils::MAX_SEQUENCE_LENGTH),
+            }));
+
+        progress.set_phase(ScanPhase::Upserting);
+        let inserted = db.insert_many_embeddings_bulk(&embedded.embeddings).await;
+        progress.set_phase(ScanPhase::Embedding);
+        match inserted {
+            Ok(skipped) => {
+                report.chunks_embedded += embedded.embeddings.len() - skipped.len();
+                report.chunks_skipped.extend(skipped);
+                progress.chunks_done(batch_len);
+                on_stored(index)?;
+            }
+            Err(error) => {
+                progress.error(format!("Error saving vectors of batch {index} in db: {error}"));
+                report.failed_batches.push(FailedBatch {
+                    index,
+                    chunks: batch_len,
+                    error,
+                });
             }
         }
     }
+    Ok(())
+}
 
-    println!("Embedding of {repo_name} finished with sucess !");
-    repo_name
+/// Link the declarations and definitions of `chunks`, the new chunks of an update, to each
+/// other and to the indexed chunks of other files, then patch the `linked` payload of the
+/// indexed points whose links changed: new ones, or ones to the `removed` or re-indexed files.
+async fn link_to_index(db: &VectorStore, chunks: &mut Vec<Chunk>, removed: &[String]) -> Result<(), String> {
+    let touched: HashSet<String> = chunks
+        .iter()
+        .flat_map(|chunk| std::iter::once(&chunk.path).chain(chunk.duplicates.iter().map(|copy| &copy.path)))
+        .chain(removed)
+        .cloned()
+        .collect();
+    if touched.is_empty() {
+        return Ok(());
+    }
+    let paths: Vec<String> = touched.iter().cloned().collect();
+    let mut indexed: Vec<Chunk> = db
+        .linkable_chunks(&utils::link_names(chunks), &paths)
+        .await
+        .map_err(|e| format!("Error reading linked chunks: {e}"))?
+        .into_iter()
+        .filter(|chunk| !touched.contains(&chunk.path))
+        .collect();
+    let before: Vec<_> = indexed.iter().map(|chunk| chunk.linked.clone()).collect();
+    // Les liens vers les fichiers modifiés sont refaits, ceux vers les fichiers supprimés disparaissent
+    for chunk in &mut indexed {
+        chunk.linked.retain(|location| !touched.contains(&location.path));
+    }
+
+    let new_chunks = chunks.len();
+    chunks.extend(indexed);
+    utils::link_declarations(chunks);
+    let changed: Vec<Chunk> = chunks
+        .split_off(new_chunks)
+        .into_iter()
+        .zip(before)
+        .filter(|(chunk, before)| &chunk.linked != before)
+        .map(|(chunk, _)| chunk)
+        .collect();
+    db.set_linked(&changed)
+        .await
+        .map_err(|e| format!("Error updating linked chunks: {e}"))
+}
+
+/// Clone `url` into `clone/<name>` (the last segment of the url by default), or fetch it
+/// when it was already cloned, and check out `git_ref`.
+/// Returns the repo name to scan and the checked out commit.
+pub fn clone_repo(
+    url: &str,
+    name: Option<&str>,
+    git_ref: Option<&str>,
+) -> Result<(String, String), String> {
+    let name = match name {
+        Some(name) if git::is_valid_repo_name(name) => name.to_string(),
+        Some(name) => return Err(format!("Invalid repo name {name}")),
+        None => git::repo_name_from_url(url)
+            .ok_or_else(|| format!("Unable to derive a repo name from {url}, give one"))?,
+    };
+
+    fs::create_dir_all("clone").map_err(|e| format!("Error creating clone/: {e}"))?;
+    let dest = format!("clone/{name}");
+    println!("Fetching {url} into {dest}");
+    let commit = git::clone_or_fetch(url, Path::new(&dest), git_ref)?;
+    println!("{dest} is at {commit}");
+    Ok((name, commit))
+}
+
+/// Files to index, files skipped, and the commit they come from.
+type ParsedRepo = (Vec<parsing::FileMeta>, Vec<SkippedFile>, Option<String>);
+
+/// Parse `clone/<repo_name>`, or the tree of `git_ref` when given.
+fn parse_files(
+    repo_dir: String,
+    git_ref: Option<&str>,
+    settings: &repo_config::ScanSettings,
+) -> Result<ParsedRepo, String> {
+    let options = settings.parse_options()?;
+    match git_ref {
+        Some(git_ref) => {
+            let (meta_files, skipped_files, commit) =
+                parsing::parse_git_ref(repo_dir, git_ref, &options)?;
+            Ok((meta_files, skipped_files, Some(commit)))
+        }
+        None => {
+            let commit = git::head_commit(&repo_dir);
+            let (meta_files, skipped_files) = parsing::parse_repo(repo_dir, &options);
+            Ok((meta_files, skipped_files, commit))
+        }
+    }
+}
+
+/// Chunks of a file, ready to be embedded, with their secrets redacted;
+/// Err(reason) when it cannot be chunked.
+fn split_meta_file(
+    splitter: &chunking::TextSplitter,
+    redactor: &redaction::Redactor,
+    meta: &parsing::FileMeta,
+) -> Result<Vec<Chunk>, String> {
+    let path = Path::new(&meta.path);
+    let extension = parsing::extension_of(meta);
+    let content = match &meta.content {
+        Some(content) => redactor.redact(content),
+        None => redactor.redact(&fs::read_to_string(path).map_err(|e| format!("chunking failed: {e}"))?),
+    };
+    // Masqué avant le découpage : un secret à cheval sur deux chunks (corps PEM...) est vu en entier
+    let mut chunks = splitter
+        .split_content(path, extension, &content)
+        .map_err(|e| format!("chunking failed: {e}"))?;
+
+    utils::assign_point_ids(&mut chunks);
+    Ok(chunks
+        .into_iter()
+        .map(|mut chunk| {
+            chunk.content_hash = utils::normalized_hash(&chunk.text);
+            chunk.file_hash = meta.hash.clone();
+            utils::prepare_chunk(chunk)
+        })
+        .collect())
+}
+
+/// Chunks of all `meta_files`, in memory, with the files that could not be chunked.
+fn chunk_files(
+    splitter: &chunking::TextSplitter,
+    redactor: &redaction::Redactor,
+    meta_files: &[parsing::FileMeta],
+    progress: Option<&ScanProgress>,
+) -> (Vec<Chunk>, Vec<SkippedFile>) {
+    let results: Vec<_> = meta_files
+        .par_iter()
+        .map(|meta| {
+            let chunks = split_meta_file(splitter, redactor, meta).map_err(|reason| SkippedFile {
+                path: meta.path.clone(),
+                reason,
+            });
+            if let Some(progress) = progress {
+                progress.file_done();
+            }
+            chunks
+        })
+        .collect();
+
+    let mut chunks = Vec::new();
+    let mut failed_files = Vec::new();
+    for result in results {
+        match result {
+            Ok(file_chunks) => chunks.extend(file_chunks),
+            Err(skipped) => failed_files.push(skipped),
+        }
+    }
+    (chunks, failed_files)
+}
+
+/// `mapped_languages` gives the language of the files chunked as another extension,
+/// see `parsing::mapped_languages`.
+fn count_chunks(
+    chunks: &[Chunk],
+    mapped_languages: &std::collections::HashMap<String, &'static str>,
+    per_language: &mut BTreeMap<String, usize>,
+    per_type: &mut BTreeMap<String, usize>,
+) {
+    for chunk in chunks {
+        let language = match mapped_languages.get(&chunk.path) {
+            Some(language) => language,
+            None => parsing::language_of(Path::new(&chunk.path)),
+        };
+        *per_language.entry(language.to_string()).or_default() += 1;
+        *per_type.entry(format!("{:?}", chunk.chunk_type)).or_default() += 1;
+    }
+}
+
+/// Dry run of `scan_repo`: parse and chunk the repo like a full scan would, and return
+/// the number of files, chunks and tokens with the embedding cost of each known model.
+/// Neither Qdrant nor any embedding API is called, and nothing is written to disk.
+pub fn estimate_scan(
+    repo_name: String,
+    git_ref: Option<String>,
+    filter_config: Option<FilterConfig>,
+) -> Result<ScanEstimate, String> {
+    let repo_dir = format!("clone/{repo_name}");
+    if !Path::new(&repo_dir).is_dir() {
+        return Err(format!("{repo_dir} does not exist"));
+    }
+
+    let settings = repo_config::ScanSettings::resolve(&repo_dir, git_ref.as_deref(), filter_config)?;
+    let (meta_files, mut files_skipped, commit) =
+        parse_files(repo_dir, git_ref.as_deref(), &settings)?;
+
+    let redactor = settings.redactor()?;
+    let (chunks, failed_files) = chunk_files(&settings.splitter(), &redactor, &meta_files, None);
+    let (chunks, chunks_deduplicated) = utils::dedupe_chunks(chunks);
+    let files = meta_files.len() - failed_files.len();
+    files_skipped.extend(failed_files);
+
+    let mut estimate = ScanEstimate {
+        collection: git::collection_name(&repo_name, git_ref.as_deref()),
+        repo: repo_name,
+        git_ref,
+        commit,
+        files,
+        files_skipped,
+        chunks: chunks.len(),
+        chunks_deduplicated,
+        secrets_redacted: redactor.counts(),
+        tokens: utils::count_tokens(&chunks),
+        ..Default::default()
+    };
+    count_chunks(
+        &chunks,
+        &parsing::mapped_languages(&meta_files),
+        &mut estimate.chunks_per_language,
+        &mut estimate.chunks_per_type,
+    );
+    estimate.costs = utils::estimate_costs(estimate.tokens);
+
+    Ok(estimate)
 }
 
+/// Answer `question` from the index of `repo_name`, or of its `git_ref` snapshot when given.
+/// `scope` restricts the context to code or documentation chunks. Empty `instructions` fall
+/// back on the ones of the `.deepwiki.toml` of the repo.
 pub async fn ask_repo(
     question: String,
     instructions: String,
     repo_name: String,
+    git_ref: Option<String>,
+    scope: ChunkScope,
     tx: Sender<String>,
 ) -> Result<(), String> {
     let config = config::Config::from_env();
     
-    let db = VectorStore::try_open(&repo_name, config.vector_dimension).await?;
+    let collection = git::collection_name(&repo_name, git_ref.as_deref());
+    let db = VectorStore::try_open(&collection, config.vector_dimension).await?;
+    for warning in db.warnings() {
+        let _ = tx.send(format!("> ⚠️ {warning}\n\n")).await;
+    }
+
+    let instructions = match instructions.trim() {
+        "" => repo_config::default_instructions(&format!("clone/{repo_name}"), git_ref.as_deref())
+            .unwrap_or_default(),
+        _ => instructions,
+    };
 
     let embedder = embedding::create_embedder();
     let q_vec = embedder.embed_question(question.clone()).await?;
 
     // Utilisation de la recherche hybride pour de meilleurs résultats
     let similar_chunks = db
-        .hybrid_search(&q_vec, &question, config.top_k)
+        .hybrid_search(&q_vec, &question, config.top_k, scope)
         .await
         .map_err(|e| format!("Hybrid search failed: {e}"))?;
 
+    // Squelette de la classe ou de l'impl des méthodes trouvées
+    let parents = db.parents_of(&similar_chunks).await.unwrap_or_else(|e| {
+        eprintln!("Warning: could not fetch the parents of the results: {e}");
+        HashMap::new()
+    });
+
     // 4. Construction du prompt contextuel pour le LLM (concatène les chunks les plus proches)
+    let mut shown = HashSet::new();
     let context = similar_chunks
         .iter()
-        .map(|c| format!("{}\n\n", c.text))
+        .map(|c| {
+            let parent = utils::parent_context(c, &parents, &mut shown);
+            format!("{parent}{}\n{}\n", c.text, utils::context_note(c))
+        })
         .collect::<String>();
 
     let prompt = format!(
-        "Answer the question below using only the following code context:\n\
+        "Answer the question below using only the following code and documentation context:\n\
          ---\n\
          {context}\n\
          ---\n\
@@ -125,22 +749,143 @@ pub async fn ask_repo(
          Answer:"
     );
 
+    complete(prompt, &tx).await
+}
+
+/// Answer `question` from every repo of the workspace `workspace_name`: each member collection
+/// is searched, the results are fused by score and every chunk is labelled with its repo,
+/// in the prompt and in the list of sources sent after the answer.
+pub async fn ask_workspace(
+    question: String,
+    instructions: String,
+    workspace_name: String,
+    scope: ChunkScope,
+    tx: Sender<String>,
+) -> Result<(), String> {
+    let config = config::Config::from_env();
+    let workspace = workspace::get(workspace::WORKSPACES_PATH, &workspace_name)?;
+
+    let embedder = embedding::create_embedder();
+    let q_vec = embedder.embed_question(question.clone()).await?;
+
+    let searches = workspace.repos.iter().map(|repo| {
+        let (q_vec, question) = (&q_vec, &question);
+        async move {
+            let db = VectorStore::try_open(repo, config.vector_dimension).await?;
+            let chunks = db
+                .hybrid_search_scored(q_vec, question, config.top_k, scope)
+                .await
+                .map_err(|e| format!("Hybrid search failed: {e}"))?;
+            let found: Vec<Chunk> = chunks.iter().map(|(chunk, _)| chunk.clone()).collect();
+            let parents = db.parents_of(&found).await.unwrap_or_else(|e| {
+                eprintln!("Warning: could not fetch the parents of the results in {repo}: {e}");
+                HashMap::new()
+            });
+            Ok::<_, String>((chunks, parents, db.warnings().to_vec()))
+        }
+    });
+    let mut results = Vec::new();
+    let mut parents = HashMap::new();
+    let mut warnings = Vec::new();
+    for (repo, searched) in workspace.repos.iter().zip(futures_util::future::join_all(searches).await) {
+        match searched {
+            Ok((chunks, repo_parents, repo_warnings)) => {
+                results.push((repo.clone(), chunks));
+                parents.extend(repo_parents);
+                warnings.extend(repo_warnings);
+            }
+            // Un dépôt pas encore indexé ou incompatible ne bloque pas les autres
+            Err(e) => {
+                eprintln!("Warning: skipping {repo} in workspace {workspace_name}: {e}");
+                warnings.push(format!("{repo} skipped: {e}"));
+            }
+        }
+    }
+    if results.is_empty() {
+        return Err(format!(
+            "No usable index in workspace {workspace_name}: {}",
+            warnings.join("; ")
+        ));
+    }
+    for warning in &warnings {
+        let _ = tx.send(format!("> ⚠️ {warning}\n\n")).await;
+    }
+    let similar_chunks = workspace::fuse(results, config.top_k as usize);
+
+    let mut shown = HashSet::new();
+    let context = similar_chunks
+        .iter()
+        .map(|(repo, c)| {
+            let parent = utils::parent_context(c, &parents, &mut shown);
+            format!("[repo: {repo}]\n{parent}{}\n{}\n", c.text, utils::context_note(c))
+        })
+        .collect::<String>();
+
+    let prompt = format!(
+        "Answer the question below using only the following code and documentation context \
+         from the repositories {repos}. Each excerpt starts with the repository it comes from; \
+         name it when you cite the excerpt, as `repo:path:lines`.\n\
+         ---\n\
+         {context}\n\
+         ---\n\
+         Question: {question}\n\
+         Instructions: {instructions} \n\
+         Answer:",
+        repos = workspace.repos.join(", ")
+    );
+
+    complete(prompt, &tx).await?;
+
+    let mut sources: Vec<String> = Vec::new();
+    for (repo, c) in &similar_chunks {
+        let source = format!(
+            "- `{repo}` — `{}:{}-{}`\n",
+            workspace::repo_relative_path(&c.path),
+            c.chunk_start_line,
+            c.chunk_end_line
+        );
+        if !sources.contains(&source) {
+            sources.push(source);
+        }
+    }
+    let _ = tx.send(format!("\n\n**Sources**\n\n{}", sources.concat())).await;
+
+    Ok(())
+}
+
+/// Stream the completion of `prompt` from the configured provider to `tx`.
+async fn complete(prompt: String, tx: &Sender<String>) -> Result<(), String> {
     println!("{prompt}");
 
     utils::calculate_ask_cost(&prompt);
 
+    let tx = tx.clone();
     let provider = std::env::var("COMPLETION_PROVIDER")
         .unwrap_or_else(|_| "mistral".to_string())
         .to_lowercase();
 
     match provider.as_str() {
         "openai" => chatter::chat_openai_stream(prompt, tx).await?,
-        "mistral" | _ => chatter::chat_mistral_stream(prompt, tx).await?,
+        _ => chatter::chat_mistral_stream(prompt, tx).await?,
     }
 
     Ok(())
 }
 
+pub fn list_workspaces() -> Result<Vec<Workspace>, String> {
+    workspace::list(workspace::WORKSPACES_PATH)
+}
+
+/// Create the workspace `name` or replace its repos (indexed collection names).
+pub fn save_workspace(name: &str, repos: Vec<String>) -> Result<Workspace, String> {
+    workspace::save(workspace::WORKSPACES_PATH, name, repos)
+}
+
+/// False when there was no such workspace.
+pub fn delete_workspace(name: &str) -> Result<bool, String> {
+    workspace::remove(workspace::WORKSPACES_PATH, name)
+}
+
 pub fn collect_repos() -> std::io::Result<Vec<String>> {
     let mut repos = Vec::new();
     for entry in fs::read_dir("./clone")? {
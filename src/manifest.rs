@@ -0,0 +1,221 @@
+use crate::config::Config;
+use crate::repo_config::ScanSettings;
+use crate::types::FilterConfig;
+use crate::utils;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Settings an index was built with, stored next to its chunk file as
+/// `generated/<collection>.manifest.json`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct IndexManifest {
+    pub collection: String,
+    pub embedding_provider: String,
+    pub embedding_model: String,
+    pub vector_dimension: usize,
+    pub chunk_size: usize,
+    pub chunk_overlap: usize,
+    #[serde(default)]
+    pub chunking_from_repo: bool, // chunk size or overlap set by the .deepwiki.toml of the repo
+    pub filter: Option<FilterConfig>,
+    #[serde(default)]
+    pub languages: BTreeMap<String, String>,
+    pub tool_version: String,
+    pub scanned_at: u64,        // unix timestamp (s)
+    pub commit: Option<String>, // indexed commit, when the repo is a git repository
+}
+
+impl IndexManifest {
+    /// Manifest of an index built now with the current config and `settings`.
+    pub fn current(collection: &str, settings: &ScanSettings, commit: Option<String>) -> Self {
+        let config = Config::from_env();
+        let (embedding_provider, embedding_model) = utils::configured_embedding_model();
+        Self {
+            collection: collection.to_string(),
+            embedding_provider,
+            embedding_model,
+            vector_dimension: config.vector_dimension,
+            chunk_size: settings.chunk_size,
+            chunk_overlap: settings.chunk_overlap,
+            chunking_from_repo: settings.chunking_from_repo,
+            filter: settings.filter.clone(),
+            languages: settings
+                .languages
+                .iter()
+                .map(|(extension, target)| (extension.clone(), target.to_string()))
+                .collect(),
+            tool_version: env!("CARGO_PKG_VERSION").to_string(),
+            scanned_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            commit,
+        }
+    }
+
+    pub fn path(collection: &str) -> String {
+        format!("generated/{collection}.manifest.json")
+    }
+
+    pub fn load(collection: &str) -> Option<Self> {
+        let path = Self::path(collection);
+        let content = fs::read_to_string(&path).ok()?;
+        match serde_json::from_str(&content) {
+            Ok(manifest) => Some(manifest),
+            Err(e) => {
+                eprintln!("Warning: ignoring invalid manifest {path}: {e}");
+                None
+            }
+        }
+    }
+
+    /// Write to a temporary file then rename, like the checkpoints.
+    pub fn save(&self) -> Result<(), String> {
+        let path = Self::path(&self.collection);
+        let json = serde_json::to_vec_pretty(self).map_err(|e| e.to_string())?;
+        fs::create_dir_all("generated").map_err(|e| format!("Error creating generated/: {e}"))?;
+        let tmp = format!("{path}.tmp");
+        fs::write(&tmp, json).map_err(|e| format!("Error writing {tmp}: {e}"))?;
+        fs::rename(&tmp, &path).map_err(|e| format!("Error writing {path}: {e}"))
+    }
+
+    /// Drop the manifest of a collection that no longer holds the points it describes.
+    pub fn remove(collection: &str) {
+        let _ = fs::remove_file(Self::path(collection));
+    }
+
+    /// Differences that make the stored vectors meaningless for the current embedder.
+    fn embedding_mismatches(&self, current: &Self) -> Vec<String> {
+        let mut mismatches = Vec::new();
+        let indexed_model = format!("{}/{}", self.embedding_provider, self.embedding_model);
+        let current_model = format!("{}/{}", current.embedding_provider, current.embedding_model);
+        if indexed_model != current_model {
+            mismatches.push(format!(
+                "embedding model {indexed_model} (now {current_model}, EMBEDDING_PROVIDER)"
+            ));
+        }
+        if self.vector_dimension != current.vector_dimension {
+            mismatches.push(format!(
+                "vector dimension {} (now {}, VECTOR_DIMENSION)",
+                self.vector_dimension, current.vector_dimension
+            ));
+        }
+        mismatches
+    }
+
+    /// Differences that only change how files are split.
+    fn chunking_mismatches(&self, current: &Self) -> Vec<String> {
+        let mut mismatches = Vec::new();
+        if self.chunk_size != current.chunk_size {
+            mismatches.push(format!(
+                "chunk size {} (now {}, CHUNK_SIZE)",
+                self.chunk_size, current.chunk_size
+            ));
+        }
+        if self.chunk_overlap != current.chunk_overlap {
+            mismatches.push(format!(
+                "chunk overlap {} (now {}, CHUNK_OVERLAP)",
+                self.chunk_overlap, current.chunk_overlap
+            ));
+        }
+        mismatches
+    }
+}
+
+/// Check that `collection` can be queried with the current config: an error when it was
+/// embedded with another model or dimension, warnings for the other differences.
+/// Chunking pinned by the .deepwiki.toml of the repo does not follow CHUNK_SIZE and CHUNK_OVERLAP.
+pub fn check_query(collection: &str) -> Result<Vec<String>, String> {
+    let Some(indexed) = IndexManifest::load(collection) else {
+        return Ok(vec![format!(
+            "{collection} has no manifest (indexed by an older version), its settings can't be checked"
+        )]);
+    };
+    let mut current = IndexManifest::current(collection, &ScanSettings::from_env(), None);
+    if indexed.chunking_from_repo {
+        current.chunk_size = indexed.chunk_size;
+        current.chunk_overlap = indexed.chunk_overlap;
+    }
+
+    let incompatible = indexed.embedding_mismatches(&current);
+    if !incompatible.is_empty() {
+        return Err(format!(
+            "{collection} was indexed with {}: re-scan it or restore the config it was built with",
+            incompatible.join(", ")
+        ));
+    }
+
+    let mut warnings: Vec<String> = indexed
+        .chunking_mismatches(&current)
+        .into_iter()
+        .map(|m| format!("{collection} was indexed with {m}, re-scan it to apply the new setting"))
+        .collect();
+    if indexed.tool_version != current.tool_version {
+        warnings.push(format!(
+            "{collection} was indexed by version {} (now {})",
+            indexed.tool_version, current.tool_version
+        ));
+    }
+    Ok(warnings)
+}
+
+/// Check that points built with the current config and `settings` can be added to `collection`
+/// (incremental scans and updates): any embedding or chunking difference is an error, and so
+/// is a missing manifest when the collection already `has_points`.
+pub fn check_update(collection: &str, settings: &ScanSettings, has_points: bool) -> Result<(), String> {
+    let Some(indexed) = IndexManifest::load(collection) else {
+        if has_points {
+            return Err(format!(
+                "{collection} has no manifest, its settings can't be checked: run a full scan to rebuild it"
+            ));
+        }
+        return Ok(());
+    };
+    let current = IndexManifest::current(collection, settings, None);
+
+    let mut incompatible = indexed.embedding_mismatches(&current);
+    incompatible.extend(indexed.chunking_mismatches(&current));
+    if incompatible.is_empty() {
+        Ok(())
+    } else {
+        Err(format!(
+            "{collection} was indexed with {}: run a full scan to rebuild it with the current config",
+            incompatible.join(", ")
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mismatches() {
+        let indexed = IndexManifest::current("repo", &ScanSettings::from_env(), None);
+        assert!(indexed.embedding_mismatches(&indexed).is_empty());
+        assert!(indexed.chunking_mismatches(&indexed).is_empty());
+
+        let mut current = indexed.clone();
+        current.embedding_model = "other-embed".to_string();
+        current.vector_dimension = 3072;
+        current.chunk_size = 500;
+        let embedding = indexed.embedding_mismatches(&current);
+        assert_eq!(embedding.len(), 2);
+        assert!(embedding[0].contains("other-embed"));
+        assert_eq!(
+            indexed.chunking_mismatches(&current),
+            vec![format!("chunk size {} (now 500, CHUNK_SIZE)", indexed.chunk_size)]
+        );
+    }
+
+    #[test]
+    fn test_missing_manifest_blocks_updates_of_non_empty_collections() {
+        let settings = ScanSettings::from_env();
+        assert!(check_update("test-no-manifest", &settings, false).is_ok());
+        assert!(check_update("test-no-manifest", &settings, true)
+            .unwrap_err()
+            .contains("no manifest"));
+    }
+}
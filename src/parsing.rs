@@ -1,12 +1,39 @@
-use ignore::Walk;
-use std::path::Path;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::{Walk, WalkBuilder};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
 use regex::Regex;
-use crate::types::FilterConfig;
+use crate::config::Config;
+use crate::types::{FilterConfig, FilterMode, PatternSyntax, SkippedFile};
+use crate::{git, utils};
 
 const CODE_EXTENSIONS: &[&str] = &[
-    "rs", "py", "js", "ts", "tsx", "jsx", "java", "cpp", "c", "go", "rb", "php", "cs",
+    "rs", "py", "js", "ts", "tsx", "jsx", "java", "cpp", "cc", "cxx", "c", "h", "hh", "hpp", "go", "rb", "php",
+    "cs",
 ];
-const DOC_EXTENSIONS: &[&str] = &["md", "markdown", "rst", "txt", "adoc", "org"];
+/// Well-known extensionless files, with the extension to chunk them as. Other extensionless
+/// files are only read when executable, to look for a shebang.
+const EXTENSIONLESS_FILES: &[(&str, &str)] = &[
+    ("Rakefile", "rb"),
+    ("Gemfile", "rb"),
+    ("Guardfile", "rb"),
+    ("Podfile", "rb"),
+    ("Vagrantfile", "rb"),
+    ("Brewfile", "rb"),
+    ("SConstruct", "py"),
+    ("SConscript", "py"),
+    ("Jakefile", "js"),
+];
+const HEADER_EXTENSIONS: &[&str] = &["h", "hh", "hpp"];
+// Marqueurs cherchés dans l'en-tête des fichiers générés (protobuf, clients OpenAPI, ...)
+const GENERATED_MARKERS: &[&str] = &["@generated", "DO NOT EDIT"];
+const GENERATED_HEADER_LINES: usize = 10;
+// Comme git : un octet nul dans les premiers 8000 octets signale un fichier binaire
+const BINARY_SNIFF_LEN: usize = 8000;
+const DOC_EXTENSIONS: &[&str] = &["md", "markdown", "rst", "txt", "adoc", "asciidoc", "org"];
+/// Read like a .gitignore, in the repo root and in any directory.
+pub const IGNORE_FILE: &str = ".deepwikiignore";
 
 #[derive(Debug, PartialEq)]
 pub enum FileType {
@@ -15,82 +42,710 @@ pub enum FileType {
     Other,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct FileMeta {
     pub path: String,
+    pub hash: String,            // sha256 of the file content
+    pub content: Option<String>, // already loaded when read from the git object database
+    pub chunk_as: Option<&'static str>, // extension given by a shebang or a `.deepwiki.toml` mapping
 }
 
-fn detect_file_type(path: &Path) -> FileType {
-    if let Some(ext) = path.extension().and_then(|s| s.to_str()) {
-        if CODE_EXTENSIONS.contains(&ext) {
-            return FileType::Code;
-        }
-        if DOC_EXTENSIONS.contains(&ext) {
-            return FileType::Doc;
+/// Safeguards against files that would crowd out real code. A limit of 0 disables it.
+#[derive(Debug, Clone)]
+pub struct ContentLimits {
+    pub max_file_size: u64,
+    pub max_line_length: usize,
+    pub max_average_line_length: usize,
+    pub skip_generated: bool,
+}
+
+impl ContentLimits {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            max_file_size: config.max_file_size,
+            max_line_length: config.max_line_length,
+            max_average_line_length: config.max_average_line_length,
+            skip_generated: config.skip_generated,
         }
     }
+}
+
+/// Extension used to chunk the file: its own, or the one given by its shebang or a mapping.
+pub fn extension_of(meta: &FileMeta) -> &str {
+    meta.chunk_as
+        .unwrap_or_else(|| file_extension(Path::new(&meta.path)))
+}
+
+/// Language of the files chunked as another extension than their own, by path.
+pub fn mapped_languages(meta_files: &[FileMeta]) -> HashMap<String, &'static str> {
+    meta_files
+        .iter()
+        .filter_map(|m| m.chunk_as.map(|ext| (m.path.clone(), language_of_extension(ext))))
+        .collect()
+}
+
+/// The supported extension equal to `extension`, None when it is not supported.
+pub fn known_extension(extension: &str) -> Option<&'static str> {
+    CODE_EXTENSIONS
+        .iter()
+        .chain(DOC_EXTENSIONS)
+        .find(|known| **known == extension)
+        .copied()
+}
+
+/// C / C++ header, whose declarations are linked to their definitions.
+pub fn is_header(path: &Path) -> bool {
+    HEADER_EXTENSIONS.contains(&file_extension(path))
+}
+
+fn file_extension(path: &Path) -> &str {
+    path.extension().and_then(|e| e.to_str()).unwrap_or("")
+}
+
+fn detect_file_type(extension: &str) -> FileType {
+    if CODE_EXTENSIONS.contains(&extension) {
+        return FileType::Code;
+    }
+    if DOC_EXTENSIONS.contains(&extension) {
+        return FileType::Doc;
+    }
     FileType::Other
 }
 
-fn path_matches_pattern(path: &str, pattern: &str) -> bool {
-    match Regex::new(pattern) {
-        Ok(regex) => regex.is_match(path),
-        Err(_) => {
-            eprintln!("Warning: Invalid regex pattern '{}', skipping", pattern);
-            false
+pub fn language_of(path: &Path) -> &'static str {
+    language_of_extension(file_extension(path))
+}
+
+fn language_of_extension(extension: &str) -> &'static str {
+    match extension {
+        "rs" => "rust",
+        "py" => "python",
+        "js" | "jsx" => "javascript",
+        "ts" | "tsx" => "typescript",
+        "java" => "java",
+        "cpp" | "cc" | "cxx" | "hh" | "hpp" => "cpp",
+        "c" | "h" => "c",
+        "go" => "go",
+        "rb" => "ruby",
+        "php" => "php",
+        "cs" => "csharp",
+        "md" | "markdown" => "markdown",
+        "rst" => "rst",
+        "adoc" | "asciidoc" => "asciidoc",
+        "org" => "org",
+        "txt" => "text",
+        _ => "other",
+    }
+}
+
+enum PathPattern {
+    Glob(Gitignore),
+    Regex(Regex),
+}
+
+impl PathPattern {
+    fn is_match(&self, relative_path: &str) -> bool {
+        match self {
+            PathPattern::Glob(glob) => glob
+                .matched_path_or_any_parents(relative_path, false)
+                .is_ignore(),
+            PathPattern::Regex(regex) => regex.is_match(relative_path),
         }
     }
 }
 
-fn should_include_file(path: &str, filter_config: &Option<FilterConfig>) -> bool {
-    match filter_config {
-        None => true,
-        Some(config) => {
-            let matches_any_pattern = config.paths.iter().any(|pattern| path_matches_pattern(path, pattern));
-            
-            match config.mode.as_str() {
-                "include" => matches_any_pattern,
-                "exclude" => !matches_any_pattern,
-                _ => true, // Default to include if mode is unknown
-            }
+/// `FilterConfig` compiled once per scan. Rules are matched against the repo-relative path
+/// and, as in a .gitignore, the last matching rule decides. A path no rule matches is kept,
+/// unless there are include rules.
+#[derive(Default)]
+pub struct PathFilter {
+    rules: Vec<(FilterMode, PathPattern)>,
+}
+
+impl PathFilter {
+    pub fn new(filter_config: Option<&FilterConfig>) -> Result<Self, String> {
+        let mut rules = Vec::new();
+        for rule in filter_config.map(|c| c.rules.as_slice()).unwrap_or_default() {
+            let pattern = match rule.syntax {
+                PatternSyntax::Glob => {
+                    if rule.pattern.starts_with('!') {
+                        return Err(format!(
+                            "Invalid glob {}: use a rule of the opposite mode instead of `!`",
+                            rule.pattern
+                        ));
+                    }
+                    let mut builder = GitignoreBuilder::new(".");
+                    builder
+                        .add_line(None, &rule.pattern)
+                        .map_err(|e| format!("Invalid glob {}: {e}", rule.pattern))?;
+                    let glob = builder
+                        .build()
+                        .map_err(|e| format!("Invalid glob {}: {e}", rule.pattern))?;
+                    PathPattern::Glob(glob)
+                }
+                PatternSyntax::Regex => PathPattern::Regex(
+                    Regex::new(&rule.pattern)
+                        .map_err(|e| format!("Invalid regex {}: {e}", rule.pattern))?,
+                ),
+            };
+            rules.push((rule.mode, pattern));
+        }
+        Ok(Self { rules })
+    }
+
+    pub fn is_included(&self, relative_path: &str) -> bool {
+        let last_match = self
+            .rules
+            .iter()
+            .rev()
+            .find(|(_, pattern)| pattern.is_match(relative_path))
+            .map(|(mode, _)| *mode);
+        match last_match {
+            Some(mode) => mode == FilterMode::Include,
+            None => !self.rules.iter().any(|(mode, _)| *mode == FilterMode::Include),
         }
     }
 }
 
-pub fn parse_repo(path: String, filter_config: Option<FilterConfig>) -> Vec<FileMeta> {
+/// What decides, beside the ignore files, which files of a repo are indexed and how.
+#[derive(Default)]
+pub struct ParseOptions {
+    pub filter: PathFilter,
+    pub languages: HashMap<String, &'static str>, // extension → supported extension to chunk it as
+}
+
+impl ParseOptions {
+    /// Extension to chunk the file as when it is mapped to another one.
+    fn mapped_extension(&self, path: &Path) -> Option<&'static str> {
+        self.languages.get(file_extension(path)).copied()
+    }
+}
+
+/// Walk of the repo honouring the .gitignore files and the `IGNORE_FILE` ones.
+fn walk(path: &str) -> Walk {
+    WalkBuilder::new(path)
+        .add_custom_ignore_filename(IGNORE_FILE)
+        .build()
+}
+
+/// Root `IGNORE_FILE` of the tree of `git_ref`; the ones of subdirectories are not read.
+fn ignore_at_ref(path: &str, git_ref: &str) -> Result<Option<Gitignore>, String> {
+    let Some(content) = git::read_file_at_ref(path, git_ref, IGNORE_FILE)? else {
+        return Ok(None);
+    };
+    let mut builder = GitignoreBuilder::new("");
+    for line in String::from_utf8_lossy(&content).lines() {
+        builder
+            .add_line(None, line)
+            .map_err(|e| format!("Invalid {IGNORE_FILE} at {git_ref}: {e}"))?;
+    }
+    let ignore = builder
+        .build()
+        .map_err(|e| format!("Invalid {IGNORE_FILE} at {git_ref}: {e}"))?;
+    Ok(Some(ignore))
+}
+
+/// Walk the repo and return the files to index, and the files skipped with the reason why.
+pub fn parse_repo(
+    path: String,
+    options: &ParseOptions,
+) -> (Vec<FileMeta>, Vec<SkippedFile>) {
+    let limits = ContentLimits::from_config(&Config::from_env());
     let mut results = Vec::new();
+    let mut skipped = Vec::new();
 
-    for entry in Walk::new(&path) {
+    for entry in walk(&path) {
         let entry = match entry {
             Ok(e) => e,
-            Err(_) => continue,
+            Err(e) => {
+                eprintln!("Warning: unable to walk {path}: {e}");
+                continue;
+            }
         };
 
+        if !entry.file_type().is_some_and(|t| t.is_file()) {
+            continue;
+        }
+
         let entry_path = entry.path();
-        let file_type = detect_file_type(entry_path);
+        let path_str = entry_path.display().to_string();
 
-        if file_type != FileType::Code {
+        // La taille est vérifiée avant de lire le fichier
+        let metadata = entry.metadata().ok();
+        let size = metadata.as_ref().map(|m| m.len()).unwrap_or_default();
+        let executable = metadata.as_ref().is_some_and(is_executable);
+        // Un chemin absolu ferait paniquer les globs, qui n'acceptent que des chemins sous leur racine
+        let Ok(relative_path) = entry_path.strip_prefix(&path) else {
+            eprintln!("Warning: {path_str} is outside of {path}, skipped");
             continue;
+        };
+        let checked = check_file(entry_path, &relative_path.to_string_lossy(), executable, options)
+            .and_then(|mapped| check_size(size, &limits).map(|()| mapped));
+        let mapped = match checked {
+            Ok(mapped) => mapped,
+            Err(reason) => {
+                skipped.push(SkippedFile {
+                    path: path_str,
+                    reason,
+                });
+                continue;
+            }
+        };
+
+        let content = match fs::read(entry_path) {
+            Ok(c) => c,
+            Err(e) => {
+                skipped.push(SkippedFile {
+                    path: path_str,
+                    reason: format!("unreadable: {e}"),
+                });
+                continue;
+            }
+        };
+        let chunk_as = match check_content(entry_path, mapped, &content, &limits) {
+            Ok(chunk_as) => chunk_as,
+            Err(reason) => {
+                skipped.push(SkippedFile {
+                    path: path_str,
+                    reason,
+                });
+                continue;
+            }
+        };
+
+        results.push(FileMeta {
+            path: path_str,
+            hash: utils::hash_content(&content),
+            content: None,
+            chunk_as,
+        });
+    }
+    (results, skipped)
+}
+
+/// Ignore files read like the walk of `parse_repo` does, but only those of the directories
+/// holding the paths checked, each read once.
+struct IgnoreMatcher {
+    root: PathBuf,
+    git: bool,
+    dirs: HashMap<PathBuf, Vec<Option<Gitignore>>>,
+    excludes: Vec<Gitignore>, // .git/info/exclude, then the global excludes
+}
+
+impl IgnoreMatcher {
+    /// Per directory, by decreasing precedence as in the walk.
+    const FILE_NAMES: [&str; 3] = [IGNORE_FILE, ".ignore", ".gitignore"];
+
+    fn new(root: &str) -> Self {
+        let root = PathBuf::from(root);
+        let git = root.join(".git").exists();
+        let mut excludes = Vec::new();
+        if git {
+            excludes.push(Gitignore::new(root.join(".git/info/exclude")).0);
+            excludes.push(Gitignore::global().0);
+        }
+        Self {
+            root,
+            git,
+            dirs: HashMap::new(),
+            excludes,
         }
+    }
 
-        let path_str = entry_path.display().to_string();
-        
-        // Apply filter if configured
-        if !should_include_file(&path_str, &filter_config) {
+    /// Whether the walk would skip `relative_path`: hidden, or ignored by an ignore file.
+    fn is_ignored(&mut self, relative_path: &Path, is_dir: bool) -> bool {
+        if relative_path
+            .components()
+            .any(|c| c.as_os_str().to_string_lossy().starts_with('.'))
+        {
+            return true;
+        }
+        let path = self.root.join(relative_path);
+        let ancestors: Vec<PathBuf> = path
+            .ancestors()
+            .skip(1)
+            .take_while(|dir| dir.starts_with(&self.root))
+            .map(Path::to_path_buf)
+            .collect();
+
+        for kind in 0..Self::FILE_NAMES.len() {
+            for dir in &ancestors {
+                let Some(ignore) = &self.ignore_files(dir)[kind] else {
+                    continue;
+                };
+                let matched = ignore.matched_path_or_any_parents(&path, is_dir);
+                if !matched.is_none() {
+                    return matched.is_ignore();
+                }
+            }
+        }
+        self.excludes
+            .iter()
+            .map(|ignore| ignore.matched_path_or_any_parents(&path, is_dir))
+            .find(|matched| !matched.is_none())
+            .is_some_and(|matched| matched.is_ignore())
+    }
+
+    fn ignore_files(&mut self, dir: &Path) -> &[Option<Gitignore>] {
+        let git = self.git;
+        self.dirs.entry(dir.to_path_buf()).or_insert_with(|| {
+            Self::FILE_NAMES
+                .iter()
+                .map(|name| {
+                    let file = dir.join(name);
+                    // Comme le walk : les .gitignore ne valent que dans un dépôt git
+                    let applies = git || *name != ".gitignore";
+                    (applies && file.is_file()).then(|| {
+                        let (ignore, error) = Gitignore::new(&file);
+                        if let Some(e) = error {
+                            eprintln!("Warning: {}: {e}", file.display());
+                        }
+                        ignore
+                    })
+                })
+                .collect()
+        })
+    }
+}
+
+/// `parse_repo` restricted to the `touched` files (relative to the repo root), e.g. after a
+/// filesystem change. Returns the files to re-index, the ones skipped, and the touched paths
+/// that are gone, ignored or excluded, whose points must be deleted.
+/// A touched directory (e.g. moved into the repo) stands for all the files under it.
+pub fn parse_touched(
+    path: String,
+    touched: &[String],
+    options: &ParseOptions,
+) -> (Vec<FileMeta>, Vec<SkippedFile>, Vec<String>) {
+    // Seuls les chemins touchés sont vérifiés, sans parcourir tout le dépôt
+    let mut ignore = IgnoreMatcher::new(&path);
+    let mut candidates = BTreeSet::new();
+    let mut gone = BTreeSet::new();
+    for relative_path in touched {
+        let path_str = format!("{path}/{relative_path}");
+        let full_path = Path::new(&path_str);
+        if full_path.is_dir() {
+            if !ignore.is_ignored(Path::new(relative_path), true) {
+                // Le walk d'un sous-dossier lit aussi les fichiers d'ignore de ses parents
+                candidates.extend(
+                    walk(&path_str)
+                        .filter_map(Result::ok)
+                        .filter(|e| e.file_type().is_some_and(|t| t.is_file()))
+                        .map(|e| e.path().display().to_string()),
+                );
+            }
+        } else if full_path.is_file() && !ignore.is_ignored(Path::new(relative_path), false) {
+            candidates.insert(path_str);
+        } else {
+            gone.insert(path_str);
+        }
+    }
+
+    let limits = ContentLimits::from_config(&Config::from_env());
+    let mut results = Vec::new();
+    let mut skipped = Vec::new();
+    let mut removed: Vec<String> = gone.into_iter().collect();
+
+    for path_str in candidates {
+        let Some(relative_path) = path_str.strip_prefix(&format!("{path}/")) else {
+            eprintln!("Warning: {path_str} is outside of {path}, skipped");
             continue;
+        };
+        let executable = fs::metadata(&path_str).is_ok_and(|m| is_executable(&m));
+        let mapped = match check_file(Path::new(&path_str), relative_path, executable, options) {
+            Ok(mapped) => mapped,
+            Err(reason) => {
+                skipped.push(SkippedFile {
+                    path: path_str.clone(),
+                    reason,
+                });
+                removed.push(path_str);
+                continue;
+            }
+        };
+
+        let checked = fs::read(&path_str)
+            .map_err(|e| format!("unreadable: {e}"))
+            .and_then(|content| {
+                check_size(content.len() as u64, &limits)?;
+                let chunk_as = check_content(Path::new(&path_str), mapped, &content, &limits)?;
+                Ok((content, chunk_as))
+            });
+        match checked {
+            Ok((content, chunk_as)) => results.push(FileMeta {
+                hash: utils::hash_content(&content),
+                path: path_str,
+                content: None,
+                chunk_as,
+            }),
+            Err(reason) => {
+                skipped.push(SkippedFile {
+                    path: path_str.clone(),
+                    reason,
+                });
+                removed.push(path_str);
+            }
+        }
+    }
+
+    (results, skipped, removed)
+}
+
+/// Same as `parse_repo`, but reads the tree of a branch, tag or commit from the
+/// git object database instead of the checked out files.
+/// Also returns the resolved commit id.
+pub fn parse_git_ref(
+    path: String,
+    git_ref: &str,
+    options: &ParseOptions,
+) -> Result<(Vec<FileMeta>, Vec<SkippedFile>, String), String> {
+    let limits = ContentLimits::from_config(&Config::from_env());
+    let ignore = ignore_at_ref(&path, git_ref)?;
+    let mut results = Vec::new();
+    let mut skipped = Vec::new();
+
+    let commit = git::walk_ref(&path, git_ref, |relative_path, executable, load| {
+        let path_str = format!("{path}/{relative_path}");
+
+        // Comme pour le walk du working tree, les fichiers ignorés n'apparaissent pas dans le rapport
+        if ignore.as_ref().is_some_and(|ignore| {
+            ignore
+                .matched_path_or_any_parents(relative_path, false)
+                .is_ignore()
+        }) {
+            return;
         }
 
+        let mapped = match check_file(Path::new(relative_path), relative_path, executable, options) {
+            Ok(mapped) => mapped,
+            Err(reason) => {
+                skipped.push(SkippedFile {
+                    path: path_str,
+                    reason,
+                });
+                return;
+            }
+        };
+
+        let checked = load()
+            .map_err(|e| format!("unreadable: {e}"))
+            .and_then(|content| {
+                check_size(content.len() as u64, &limits)?;
+                let chunk_as = check_content(Path::new(relative_path), mapped, &content, &limits)?;
+                // check_content garantit de l'UTF-8
+                let content = String::from_utf8(content).map_err(|e| format!("unreadable: {e}"))?;
+                Ok((content, chunk_as))
+            });
+        let (content, chunk_as) = match checked {
+            Ok(checked) => checked,
+            Err(reason) => {
+                skipped.push(SkippedFile {
+                    path: path_str,
+                    reason,
+                });
+                return;
+            }
+        };
+
         results.push(FileMeta {
             path: path_str,
+            hash: utils::hash_content(content.as_bytes()),
+            content: Some(content),
+            chunk_as,
         });
+    })?;
+
+    Ok((results, skipped, commit))
+}
+
+/// Err(reason) when the file must not be indexed, judging by its path.
+/// Well-known extensionless files are mapped by name; the other ones pass only when
+/// `executable`: their type comes from their shebang, see `check_content`.
+/// Returns the extension the file is mapped to, if any.
+fn check_file(
+    path: &Path,
+    relative_path: &str,
+    executable: bool,
+    options: &ParseOptions,
+) -> Result<Option<&'static str>, String> {
+    let mapped = options.mapped_extension(path).or_else(|| extensionless_extension(path));
+    let extension = mapped.unwrap_or_else(|| file_extension(path));
+    if mapped.is_none() && path.extension().is_none() {
+        if !executable {
+            return Err("unsupported file type (no extension, not executable)".to_string());
+        }
+    } else if detect_file_type(extension) == FileType::Other {
+        return Err("unsupported file type".to_string());
+    }
+
+    // Apply filter if configured
+    if !options.filter.is_included(relative_path) {
+        return Err("excluded by filter".to_string());
+    }
+
+    Ok(mapped)
+}
+
+/// Extension to chunk a well-known extensionless file as, e.g. `Rakefile` → `rb`.
+fn extensionless_extension(path: &Path) -> Option<&'static str> {
+    if path.extension().is_some() {
+        return None;
+    }
+    let name = path.file_name()?.to_str()?;
+    EXTENSIONLESS_FILES
+        .iter()
+        .find(|(known, _)| *known == name)
+        .map(|(_, extension)| *extension)
+}
+
+#[cfg(unix)]
+fn is_executable(metadata: &fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o111 != 0
+}
+
+// Pas de bit d'exécution : tout fichier sans extension est lu pour chercher un shebang
+#[cfg(not(unix))]
+fn is_executable(_metadata: &fs::Metadata) -> bool {
+    true
+}
+
+fn check_size(size: u64, limits: &ContentLimits) -> Result<(), String> {
+    if limits.max_file_size > 0 && size > limits.max_file_size {
+        return Err(format!(
+            "too large: {size} bytes (MAX_FILE_SIZE is {})",
+            limits.max_file_size
+        ));
+    }
+    Ok(())
+}
+
+/// Err(reason) when the content of the file must not be indexed: binary, generated or minified.
+/// Returns the extension to chunk the file as: `mapped`, or the one implied by the shebang
+/// of an extensionless file.
+fn check_content(
+    path: &Path,
+    mapped: Option<&'static str>,
+    content: &[u8],
+    limits: &ContentLimits,
+) -> Result<Option<&'static str>, String> {
+    if content[..content.len().min(BINARY_SNIFF_LEN)].contains(&0) {
+        return Err("binary file".to_string());
+    }
+    let text = std::str::from_utf8(content).map_err(|_| "binary file (not UTF-8)".to_string())?;
+
+    let chunk_as = match path.extension() {
+        _ if mapped.is_some() => mapped,
+        Some(_) => None,
+        None => match text.lines().next().and_then(shebang_extension) {
+            Some(extension) => Some(extension),
+            None => return Err("unsupported file type (no extension, no known shebang)".to_string()),
+        },
+    };
+
+    if limits.skip_generated {
+        let header = text.lines().take(GENERATED_HEADER_LINES);
+        if let Some(marker) = header
+            .flat_map(|line| GENERATED_MARKERS.iter().filter(move |m| line.contains(**m)))
+            .next()
+        {
+            return Err(format!("generated file (`{marker}` header)"));
+        }
+    }
+
+    // Les longues lignes sont normales dans la documentation (un paragraphe par ligne)
+    let extension = chunk_as.unwrap_or_else(|| file_extension(path));
+    if detect_file_type(extension) == FileType::Code {
+        check_minified(path, text, limits)?;
+    }
+
+    Ok(chunk_as)
+}
+
+fn check_minified(path: &Path, text: &str, limits: &ContentLimits) -> Result<(), String> {
+    let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    if file_name.contains(".min.") {
+        return Err("minified file".to_string());
+    }
+
+    let lengths: Vec<usize> = text.lines().map(|l| l.chars().count()).collect();
+    let longest = lengths.iter().copied().max().unwrap_or(0);
+    if limits.max_line_length > 0 && longest > limits.max_line_length {
+        return Err(format!(
+            "minified: a line of {longest} characters (MAX_LINE_LENGTH is {})",
+            limits.max_line_length
+        ));
+    }
+    let average = lengths.iter().sum::<usize>() / lengths.len().max(1);
+    if limits.max_average_line_length > 0 && average > limits.max_average_line_length {
+        return Err(format!(
+            "minified: {average} characters per line on average (MAX_AVERAGE_LINE_LENGTH is {})",
+            limits.max_average_line_length
+        ));
+    }
+    Ok(())
+}
+
+/// Extension matching the interpreter of a `#!` line, e.g. `#!/usr/bin/env python3` → `py`.
+fn shebang_extension(first_line: &str) -> Option<&'static str> {
+    let mut words = first_line.strip_prefix("#!")?.split_whitespace();
+    let mut interpreter = words.next()?.rsplit('/').next()?;
+    if interpreter == "env" {
+        interpreter = words.find(|w| !w.starts_with('-') && !w.contains('='))?;
+    }
+    // python3.12 → python
+    let name = interpreter.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+    match name {
+        "python" | "pypy" => Some("py"),
+        "node" | "nodejs" | "deno" | "bun" => Some("js"),
+        "ts-node" | "tsx" => Some("ts"),
+        "ruby" => Some("rb"),
+        "php" => Some("php"),
+        _ => None,
+    }
+}
+
+/// Compare parsed files with the hashes already indexed.
+/// Returns the files to (re-)index and the paths whose points must be deleted.
+pub fn diff_with_index(
+    meta_files: Vec<FileMeta>,
+    indexed: &HashMap<String, String>,
+) -> (Vec<FileMeta>, Vec<String>) {
+    let current: HashSet<&str> = meta_files.iter().map(|m| m.path.as_str()).collect();
+
+    let mut to_delete: Vec<String> = indexed
+        .keys()
+        .filter(|path| !current.contains(path.as_str()))
+        .cloned()
+        .collect();
+
+    let mut to_index = Vec::new();
+    for meta in meta_files {
+        match indexed.get(&meta.path) {
+            Some(hash) if *hash == meta.hash => continue,
+            Some(_) => to_delete.push(meta.path.clone()),
+            None => (),
+        }
+        to_index.push(meta);
     }
-    results
+
+    (to_index, to_delete)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn filter(json: &str) -> PathFilter {
+        let config: FilterConfig = serde_json::from_str(json).unwrap();
+        PathFilter::new(Some(&config)).unwrap()
+    }
+
+    fn path_matches_pattern(path: &str, pattern: &str) -> bool {
+        let rule = serde_json::json!({"rules": [{"mode": "include", "pattern": pattern, "syntax": "regex"}]});
+        filter(&rule.to_string()).is_included(path)
+    }
+
     #[test]
     fn test_regex_patterns() {
         // Exact match
@@ -114,31 +769,218 @@ mod tests {
         assert!(path_matches_pattern("src/test_utils.rs", "test"));
         assert!(path_matches_pattern("test_main.rs", "test"));
         assert!(path_matches_pattern("test_main.rs", "test*"));
-        assert!(!path_matches_pattern("test_main.rs", "*test"));
         assert!(!path_matches_pattern("src/main.rs", "test"));
+
+        // Invalid regex: the filter is refused instead of silently matching nothing
+        let invalid: FilterConfig = serde_json::from_value(
+            serde_json::json!({"rules": [{"mode": "include", "pattern": "*test", "syntax": "regex"}]}),
+        )
+        .unwrap();
+        assert!(PathFilter::new(Some(&invalid)).err().is_some_and(|e| e.contains("Invalid regex")));
     }
 
     #[test]
     fn test_filter_config_exclude() {
-        let filter_config = Some(FilterConfig {
-            mode: "exclude".to_string(),
-            paths: vec!["node_modules".to_string(), r"\.test\.js$".to_string()],
-        });
+        let filter = filter(r#"{"mode": "exclude", "paths": ["node_modules", "\\.test\\.js$"]}"#);
 
-        assert!(!should_include_file("node_modules/package.json", &filter_config));
-        assert!(!should_include_file("src/main.test.js", &filter_config));
-        assert!(should_include_file("src/main.js", &filter_config));
+        assert!(!filter.is_included("node_modules/package.json"));
+        assert!(!filter.is_included("src/main.test.js"));
+        assert!(filter.is_included("src/main.js"));
     }
 
     #[test]
     fn test_filter_config_include() {
-        let filter_config = Some(FilterConfig {
-            mode: "include".to_string(),
-            paths: vec!["^src/".to_string(), r"\.rs$".to_string()],
-        });
+        let filter = filter(r#"{"mode": "include", "paths": ["^src/", "\\.rs$"]}"#);
+
+        assert!(filter.is_included("src/main.js"));
+        assert!(filter.is_included("main.rs"));
+        assert!(!filter.is_included("docs/readme.md"));
+    }
+
+    #[test]
+    fn test_ordered_glob_rules() {
+        let filter = filter(
+            r#"{"rules": [
+                {"mode": "include", "pattern": "services/**"},
+                {"mode": "exclude", "pattern": "**/generated/**"},
+                {"mode": "exclude", "pattern": "*_test.go"},
+                {"mode": "include", "pattern": "services/api/generated/keep.go"}
+            ]}"#,
+        );
+
+        assert!(filter.is_included("services/api/main.go"));
+        assert!(!filter.is_included("services/api/main_test.go"));
+        assert!(!filter.is_included("services/api/generated/client.go"));
+        assert!(filter.is_included("services/api/generated/keep.go"));
+        assert!(!filter.is_included("tools/main.go"));
+        assert!(PathFilter::default().is_included("tools/main.go"));
+
+        let unknown = serde_json::from_str::<FilterConfig>(r#"{"mode": "only", "paths": ["src"]}"#);
+        assert!(unknown.unwrap_err().to_string().contains("Unknown filter mode only"));
+        let invalid = serde_json::json!({"rules": [{"mode": "include", "pattern": "*test", "syntax": "regex"}]});
+        let invalid: FilterConfig = serde_json::from_value(invalid).unwrap();
+        assert!(PathFilter::new(Some(&invalid)).is_err());
+    }
+
+    #[test]
+    fn test_check_content() {
+        let limits = ContentLimits::from_config(&Config::default());
+        let check = |path: &str, content: &str| check_content(Path::new(path), None, content.as_bytes(), &limits);
+
+        assert_eq!(check("src/main.rs", "fn main() {}\n"), Ok(None));
+        assert_eq!(check("bin/deploy", "#!/usr/bin/env python3\nprint(1)\n"), Ok(Some("py")));
+        assert!(check("bin/run", "#!/bin/bash -e\necho hi\n").is_err());
+        assert!(check("LICENSE", "MIT License\n").is_err());
+
+        // Extensionless files are only read when well-known or executable
+        let options = ParseOptions::default();
+        assert_eq!(check_file(Path::new("Rakefile"), "Rakefile", false, &options), Ok(Some("rb")));
+        assert!(check_file(Path::new("LICENSE"), "LICENSE", false, &options).is_err());
+        assert_eq!(check_file(Path::new("bin/deploy"), "bin/deploy", true, &options), Ok(None));
+        assert!(check_file(Path::new("run.sh"), "run.sh", true, &options).is_err());
+        assert_eq!(check_content(Path::new("a.rs"), None, &[b'x', 0, b'y'], &limits), Err("binary file".to_string()));
+        assert!(check("api.pb.go", "// Code generated by protoc-gen-go. DO NOT EDIT.\npackage api\n")
+            .unwrap_err()
+            .starts_with("generated"));
+        assert!(check("app.js", &format!("var a={};\n", "x".repeat(3000))).unwrap_err().starts_with("minified"));
+        assert!(check("vendor.min.js", "var a;\n").is_err());
+        // Documentation paragraphs are often written on a single long line
+        assert!(check("README.md", &format!("{}\n", "word ".repeat(1000))).is_ok());
+        assert!(check_size(2_000_000, &limits).is_err());
+    }
+
+    #[test]
+    fn test_diff_with_index() {
+        let meta = |path: &str, hash: &str| FileMeta {
+            path: path.to_string(),
+            hash: hash.to_string(),
+            content: None,
+            chunk_as: None,
+        };
+        let indexed: HashMap<String, String> = [
+            ("clone/r/same.rs", "h1"),
+            ("clone/r/changed.rs", "h2"),
+            ("clone/r/removed.rs", "h3"),
+        ]
+        .into_iter()
+        .map(|(p, h)| (p.to_string(), h.to_string()))
+        .collect();
+
+        let (to_index, mut to_delete) = diff_with_index(
+            vec![
+                meta("clone/r/same.rs", "h1"),
+                meta("clone/r/changed.rs", "h2bis"),
+                meta("clone/r/added.rs", "h4"),
+            ],
+            &indexed,
+        );
+        to_delete.sort();
+
+        let indexed_paths: Vec<_> = to_index.iter().map(|m| m.path.as_str()).collect();
+        assert_eq!(indexed_paths, vec!["clone/r/changed.rs", "clone/r/added.rs"]);
+        assert_eq!(to_delete, vec!["clone/r/changed.rs", "clone/r/removed.rs"]);
+    }
+
+    #[test]
+    fn test_parse_touched() {
+        let dir = std::env::temp_dir().join(format!("bdw-parse-touched-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(dir.join("src/main.rs"), "fn main() {}").unwrap();
+        fs::write(dir.join("logo.png"), [0u8, 1, 2]).unwrap();
+        fs::create_dir_all(dir.join("src/gen")).unwrap();
+        fs::write(dir.join("src/gen/api.rs"), "fn api() {}").unwrap();
+        fs::write(dir.join("src/gen/keep.rs"), "fn keep() {}").unwrap();
+        fs::write(dir.join("src").join(IGNORE_FILE), "gen/\n!gen/keep.rs\n").unwrap();
+
+        let root = dir.display().to_string();
+        let touched =
+            ["src/main.rs", "logo.png", "src/deleted.rs", "src/gen/api.rs"].map(String::from);
+        let (files, skipped, removed) = parse_touched(root.clone(), &touched, &ParseOptions::default());
+
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, format!("{root}/src/main.rs"));
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(
+            removed,
+            vec![
+                format!("{root}/src/deleted.rs"),
+                format!("{root}/src/gen/api.rs"),
+                format!("{root}/logo.png")
+            ]
+        );
+
+        let (files, _, _) = parse_touched(root.clone(), &["src".to_string()], &ParseOptions::default());
+        let _ = fs::remove_dir_all(&dir);
+        let mut paths: Vec<&str> = files.iter().map(|f| f.path.as_str()).collect();
+        paths.sort();
+        assert_eq!(paths, vec![format!("{root}/src/main.rs")]);
+    }
+
+    #[test]
+    fn test_parse_repo_honours_ignore_file_and_mappings() {
+        let dir = std::env::temp_dir().join(format!("bdw-parse-ignore-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::create_dir_all(dir.join("vendor")).unwrap();
+        fs::write(dir.join(IGNORE_FILE), "vendor/\n").unwrap();
+        fs::write(dir.join("vendor/dep.rs"), "fn dep() {}").unwrap();
+        fs::write(dir.join("src/main.rs"), "fn main() {}").unwrap();
+        fs::write(dir.join("src/tool.mjs"), "export const a = 1;").unwrap();
+
+        let options = ParseOptions {
+            languages: HashMap::from([("mjs".to_string(), "js")]),
+            ..Default::default()
+        };
+        let root = dir.display().to_string();
+        let (mut files, skipped) = parse_repo(root.clone(), &options);
+        let _ = fs::remove_dir_all(&dir);
+
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        let parsed: Vec<_> = files.iter().map(|m| (m.path.clone(), extension_of(m).to_string())).collect();
+        assert_eq!(
+            parsed,
+            vec![
+                (format!("{root}/src/main.rs"), "rs".to_string()),
+                (format!("{root}/src/tool.mjs"), "js".to_string()),
+            ]
+        );
+        assert!(skipped.is_empty());
+    }
+
+    #[test]
+    fn test_parse_git_ref_reads_committed_tree() {
+        let dir = std::env::temp_dir().join(format!("bdw-parse-git-ref-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let repo = git2::Repository::init(&dir).unwrap();
+        fs::write(dir.join("main.rs"), "fn main() {}").unwrap();
+        fs::write(dir.join("logo.png"), [0u8, 1, 2]).unwrap();
+        fs::create_dir_all(dir.join("vendor")).unwrap();
+        fs::write(dir.join("vendor/dep.rs"), "fn dep() {}").unwrap();
+        fs::write(dir.join(IGNORE_FILE), "vendor/\n").unwrap();
+
+        let mut index = repo.index().unwrap();
+        for path in ["main.rs", "logo.png", "vendor/dep.rs", IGNORE_FILE] {
+            index.add_path(Path::new(path)).unwrap();
+        }
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = git2::Signature::now("test", "test@example.com").unwrap();
+        let commit = repo
+            .commit(Some("HEAD"), &signature, &signature, "init", &tree, &[])
+            .unwrap();
+
+        // Working tree changes must not leak into the indexed ref
+        fs::write(dir.join("main.rs"), "fn main() { changed() }").unwrap();
+
+        let root = dir.display().to_string();
+        let (files, skipped, resolved) = parse_git_ref(root.clone(), "HEAD", &ParseOptions::default()).unwrap();
+        let _ = fs::remove_dir_all(&dir);
 
-        assert!(should_include_file("src/main.js", &filter_config));
-        assert!(should_include_file("main.rs", &filter_config));
-        assert!(!should_include_file("docs/readme.md", &filter_config));
+        assert_eq!(resolved, commit.to_string());
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, format!("{root}/main.rs"));
+        assert_eq!(files[0].content.as_deref(), Some("fn main() {}"));
+        let skipped: Vec<_> = skipped.iter().map(|s| s.path.clone()).collect();
+        assert_eq!(skipped, vec![format!("{root}/{IGNORE_FILE}"), format!("{root}/logo.png")]);
     }
 }
@@ -0,0 +1,203 @@
+use rand::Rng;
+use reqwest::header::RETRY_AFTER;
+use reqwest::{RequestBuilder, Response, StatusCode};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant, SystemTime};
+
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// Requests-per-minute and tokens-per-minute budget of an API, over a sliding one minute window.
+/// A limit of 0 disables it.
+pub struct RateLimiter {
+    rpm: usize,
+    tpm: usize,
+    window: Mutex<VecDeque<(Instant, usize)>>,
+}
+
+impl RateLimiter {
+    pub fn new(rpm: usize, tpm: usize) -> Self {
+        Self {
+            rpm,
+            tpm,
+            window: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Limiter of `provider` shared by the whole process, so that concurrent scans split
+    /// the same budget. Reads `<PROVIDER>_EMBEDDING_RPM` and `<PROVIDER>_EMBEDDING_TPM`.
+    pub fn for_provider(provider: &str, default_rpm: usize, default_tpm: usize) -> Arc<Self> {
+        static LIMITERS: OnceLock<Mutex<HashMap<String, Arc<RateLimiter>>>> = OnceLock::new();
+
+        let env = |suffix: &str, default: usize| {
+            std::env::var(format!("{}_EMBEDDING_{suffix}", provider.to_uppercase()))
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(default)
+        };
+
+        LIMITERS
+            .get_or_init(Default::default)
+            .lock()
+            .unwrap()
+            .entry(provider.to_string())
+            .or_insert_with(|| Arc::new(Self::new(env("RPM", default_rpm), env("TPM", default_tpm))))
+            .clone()
+    }
+
+    /// Wait until a request of `tokens` tokens fits in the budget, and record it.
+    pub async fn acquire(&self, tokens: usize) {
+        while let Some(wait) = self.try_acquire(tokens, Instant::now()) {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// None when the request was recorded, otherwise how long to wait before trying again.
+    fn try_acquire(&self, tokens: usize, now: Instant) -> Option<Duration> {
+        let mut window = self.window.lock().unwrap();
+        while window
+            .front()
+            .is_some_and(|(at, _)| now.duration_since(*at) >= WINDOW)
+        {
+            window.pop_front();
+        }
+
+        let used: usize = window.iter().map(|(_, t)| t).sum();
+        let fits_requests = self.rpm == 0 || window.len() < self.rpm;
+        // Une requête plus grosse que tout le budget passe quand la fenêtre est vide
+        let fits_tokens = self.tpm == 0 || used + tokens <= self.tpm || window.is_empty();
+        if fits_requests && fits_tokens {
+            window.push_back((now, tokens));
+            return None;
+        }
+
+        let (oldest, _) = window.front()?;
+        Some(WINDOW.saturating_sub(now.duration_since(*oldest)))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    pub fn new(max_retries: u32) -> Self {
+        Self {
+            max_retries,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(60),
+        }
+    }
+
+    /// Exponential backoff with jitter: a random delay between half and all of
+    /// `base_delay * 2^attempt`, capped to `max_delay`.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let ceiling = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+        ceiling.mul_f64(0.5 + rand::rng().random::<f64>() / 2.0)
+    }
+}
+
+/// Send the request built by `build` once the rate limiter allows it, retrying network
+/// errors, 408, 429 and 5xx responses. Waits for `Retry-After` (at most `max_delay`)
+/// when the API sends one.
+pub async fn send_with_retry(
+    limiter: &RateLimiter,
+    policy: &RetryPolicy,
+    tokens: usize,
+    build: impl Fn() -> RequestBuilder,
+) -> Result<Response, String> {
+    let mut attempt = 0;
+    loop {
+        limiter.acquire(tokens).await;
+
+        let (error, retry_after) = match build().send().await {
+            Ok(res) if res.status().is_success() => return Ok(res),
+            Ok(res) => {
+                let status = res.status();
+                let retry_after = retry_after(&res);
+                let body = res
+                    .text()
+                    .await
+                    .unwrap_or_else(|e| format!("(error reading body: {e})"));
+                let error = format!("status {status} — body: {body}");
+                if !is_retryable(status) {
+                    return Err(error);
+                }
+                (error, retry_after)
+            }
+            Err(e) => (format!("HTTP error: {e}"), None),
+        };
+
+        if attempt >= policy.max_retries {
+            return Err(format!("{error} (gave up after {attempt} retries)"));
+        }
+        // Un Retry-After de plusieurs heures bloquerait le scan : borné comme le backoff
+        let delay = retry_after
+            .map(|delay| delay.min(policy.max_delay))
+            .unwrap_or_else(|| policy.backoff(attempt));
+        tracing::warn!(
+            "Request failed ({error}), retry {}/{} in {:.1}s",
+            attempt + 1,
+            policy.max_retries,
+            delay.as_secs_f64()
+        );
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+fn is_retryable(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS
+        || status == StatusCode::REQUEST_TIMEOUT
+        || status.is_server_error()
+}
+
+/// `Retry-After` as a number of seconds or an HTTP date.
+fn retry_after(res: &Response) -> Option<Duration> {
+    let value = res.headers().get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<f64>() {
+        return Duration::try_from_secs_f64(seconds).ok();
+    }
+    let date = httpdate::parse_http_date(value).ok()?;
+    Some(date.duration_since(SystemTime::now()).unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limiter_budgets() {
+        let now = Instant::now();
+
+        let limiter = RateLimiter::new(2, 0);
+        assert!(limiter.try_acquire(10, now).is_none());
+        assert!(limiter.try_acquire(10, now).is_none());
+        assert_eq!(limiter.try_acquire(10, now), Some(WINDOW));
+        assert!(limiter.try_acquire(10, now + WINDOW).is_none());
+
+        let limiter = RateLimiter::new(0, 100);
+        assert!(limiter.try_acquire(60, now).is_none());
+        assert!(limiter.try_acquire(60, now + Duration::from_secs(10)).is_some());
+        assert!(limiter.try_acquire(40, now + Duration::from_secs(10)).is_none());
+        // Larger than the whole budget: waits for an empty window, then goes through
+        assert!(limiter.try_acquire(500, now + Duration::from_secs(20)).is_some());
+        assert!(limiter.try_acquire(500, now + Duration::from_secs(80)).is_none());
+    }
+
+    #[test]
+    fn test_backoff_grows_and_is_capped() {
+        let policy = RetryPolicy::new(5);
+        for attempt in 0..10 {
+            let ceiling = (policy.base_delay * 2u32.pow(attempt)).min(policy.max_delay);
+            let delay = policy.backoff(attempt);
+            assert!(delay >= ceiling / 2 && delay <= ceiling);
+        }
+    }
+}
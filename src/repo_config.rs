@@ -0,0 +1,215 @@
+use crate::config::Config;
+use crate::types::{FilterConfig, FilterRule};
+use crate::{chunking, git, parsing, redaction};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::ErrorKind;
+
+pub const REPO_CONFIG_FILE: &str = ".deepwiki.toml";
+
+/// `.deepwiki.toml` at the root of a repo: the settings its owners want for every scan of it.
+///
+/// ```toml
+/// chunk_size = 500
+/// chunk_overlap = 50
+/// instructions = "Answer in English, cite the files"
+/// redaction_allowlist = ["^pk_test_"]
+///
+/// [languages]
+/// mjs = "js"
+///
+/// [[rules]]
+/// mode = "exclude"
+/// pattern = "**/fixtures/**"
+/// ```
+#[derive(Deserialize, Debug, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct RepoConfig {
+    pub chunk_size: Option<usize>,
+    pub chunk_overlap: Option<usize>,
+    #[serde(default)]
+    pub rules: Vec<FilterRule>, // applied before the rules of the scan request
+    #[serde(default)]
+    pub languages: BTreeMap<String, String>, // extension → supported extension to chunk it as
+    pub instructions: Option<String>, // default instructions of ask_repo
+    #[serde(default)]
+    pub redaction_allowlist: Vec<String>, // regexes of detected secrets to keep as is
+}
+
+impl RepoConfig {
+    pub fn parse(content: &str) -> Result<Self, String> {
+        toml::from_str(content).map_err(|e| format!("Invalid {REPO_CONFIG_FILE}: {e}"))
+    }
+
+    /// Config of the working tree of `repo_dir`, or of the tree of `git_ref` when given.
+    /// The default config when the repo has none.
+    pub fn load(repo_dir: &str, git_ref: Option<&str>) -> Result<Self, String> {
+        let content = match git_ref {
+            Some(git_ref) => git::read_file_at_ref(repo_dir, git_ref, REPO_CONFIG_FILE)?,
+            None => match fs::read(format!("{repo_dir}/{REPO_CONFIG_FILE}")) {
+                Ok(content) => Some(content),
+                Err(e) if e.kind() == ErrorKind::NotFound => None,
+                Err(e) => return Err(format!("Error reading {repo_dir}/{REPO_CONFIG_FILE}: {e}")),
+            },
+        };
+        match content {
+            Some(content) => {
+                let content = String::from_utf8(content)
+                    .map_err(|_| format!("Invalid {REPO_CONFIG_FILE}: not UTF-8"))?;
+                Self::parse(&content)
+            }
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Language mappings checked against the supported extensions.
+    fn mapped_extensions(&self) -> Result<HashMap<String, &'static str>, String> {
+        self.languages
+            .iter()
+            .map(|(extension, target)| {
+                let known = parsing::known_extension(target.trim_start_matches('.')).ok_or_else(|| {
+                    format!("Invalid {REPO_CONFIG_FILE}: {extension} is mapped to {target}, which is not a supported extension")
+                })?;
+                Ok((extension.trim_start_matches('.').to_string(), known))
+            })
+            .collect()
+    }
+}
+
+/// Settings of a scan of one repo: the env config, overridden by the `.deepwiki.toml` of the
+/// repo, and its filter rules followed by the ones of the request, which win.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScanSettings {
+    pub chunk_size: usize,
+    pub chunk_overlap: usize,
+    pub chunking_from_repo: bool, // chunk size or overlap set by the repo config
+    pub filter: Option<FilterConfig>,
+    pub languages: HashMap<String, &'static str>,
+    pub redaction_allowlist: Vec<String>,
+}
+
+impl ScanSettings {
+    /// Settings of a repo without `.deepwiki.toml` scanned without filter.
+    pub fn from_env() -> Self {
+        let config = Config::from_env();
+        Self {
+            chunk_size: config.chunk_size,
+            chunk_overlap: config.chunk_overlap,
+            chunking_from_repo: false,
+            filter: None,
+            languages: HashMap::new(),
+            redaction_allowlist: Vec::new(),
+        }
+    }
+
+    /// Settings of a scan of `repo_dir`, or of its `git_ref` snapshot, with `request_filter`.
+    pub fn resolve(
+        repo_dir: &str,
+        git_ref: Option<&str>,
+        request_filter: Option<FilterConfig>,
+    ) -> Result<Self, String> {
+        let repo_config = RepoConfig::load(repo_dir, git_ref)?;
+        Self::merge(repo_config, request_filter)
+    }
+
+    fn merge(repo_config: RepoConfig, request_filter: Option<FilterConfig>) -> Result<Self, String> {
+        let mut settings = Self::from_env();
+        settings.languages = repo_config.mapped_extensions()?;
+        settings.redaction_allowlist = repo_config.redaction_allowlist;
+        settings.chunking_from_repo =
+            repo_config.chunk_size.is_some() || repo_config.chunk_overlap.is_some();
+        settings.chunk_size = repo_config.chunk_size.unwrap_or(settings.chunk_size);
+        settings.chunk_overlap = repo_config.chunk_overlap.unwrap_or(settings.chunk_overlap);
+        // Un recouvrement aussi grand que le chunk empêcherait le découpage d'avancer
+        if settings.chunking_from_repo && settings.chunk_overlap >= settings.chunk_size {
+            return Err(format!(
+                "Invalid {REPO_CONFIG_FILE}: chunk_overlap ({}) must be smaller than chunk_size ({})",
+                settings.chunk_overlap, settings.chunk_size
+            ));
+        }
+
+        let mut rules = repo_config.rules;
+        rules.extend(request_filter.map(|f| f.rules).unwrap_or_default());
+        settings.filter = (!rules.is_empty()).then_some(FilterConfig { rules });
+        Ok(settings)
+    }
+
+    pub fn splitter(&self) -> chunking::TextSplitter {
+        chunking::TextSplitter {
+            chunk_size: self.chunk_size,
+            chunk_overlap: self.chunk_overlap,
+        }
+    }
+
+    pub fn redactor(&self) -> Result<redaction::Redactor, String> {
+        let config = Config::from_env();
+        redaction::Redactor::new(
+            config.redact_secrets,
+            config.redaction_min_entropy,
+            &self.redaction_allowlist,
+        )
+    }
+
+    pub fn parse_options(&self) -> Result<parsing::ParseOptions, String> {
+        Ok(parsing::ParseOptions {
+            filter: parsing::PathFilter::new(self.filter.as_ref())?,
+            languages: self.languages.clone(),
+        })
+    }
+}
+
+/// Default `ask_repo` instructions of the repo, from its `.deepwiki.toml`.
+pub fn default_instructions(repo_dir: &str, git_ref: Option<&str>) -> Option<String> {
+    match RepoConfig::load(repo_dir, git_ref) {
+        Ok(config) => config.instructions,
+        Err(e) => {
+            eprintln!("Warning: ignoring the instructions of {repo_dir}: {e}");
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::FilterMode;
+
+    #[test]
+    fn test_repo_config_overrides_and_rules_order() {
+        let repo_config = RepoConfig::parse(
+            r#"
+            chunk_size = 500
+            instructions = "Cite the files"
+
+            [languages]
+            ".mjs" = "js"
+
+            [[rules]]
+            mode = "exclude"
+            pattern = "**/fixtures/**"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(repo_config.instructions.as_deref(), Some("Cite the files"));
+
+        let request: FilterConfig =
+            serde_json::from_str(r#"{"rules": [{"mode": "include", "pattern": "tests/fixtures/keep.rs"}]}"#).unwrap();
+        let settings = ScanSettings::merge(repo_config, Some(request)).unwrap();
+        assert_eq!(settings.chunk_size, 500);
+        assert!(settings.chunking_from_repo);
+        assert_eq!(settings.languages.get("mjs"), Some(&"js"));
+        let modes: Vec<_> = settings.filter.as_ref().unwrap().rules.iter().map(|r| r.mode).collect();
+        assert_eq!(modes, vec![FilterMode::Exclude, FilterMode::Include]);
+        let filter = settings.parse_options().unwrap().filter;
+        assert!(!filter.is_included("tests/fixtures/data.rs"));
+        assert!(filter.is_included("tests/fixtures/keep.rs"));
+
+        assert_eq!(ScanSettings::merge(RepoConfig::default(), None).unwrap(), ScanSettings::from_env());
+        assert!(RepoConfig::parse("chunk_sise = 500").is_err());
+        let unknown = RepoConfig::parse("[languages]\nfoo = \"cobol\"").unwrap();
+        assert!(ScanSettings::merge(unknown, None).is_err());
+        let overlap = RepoConfig::parse("chunk_size = 100\nchunk_overlap = 100").unwrap();
+        assert!(ScanSettings::merge(overlap, None).is_err());
+    }
+}
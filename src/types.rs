@@ -1,14 +1,94 @@
 use bincode::{Decode, Encode};
 use serde::{Deserialize, Serialize};
-use uuid::Uuid;
+use std::collections::BTreeMap;
 
-#[derive(Debug, Clone)]
+/// Ordered include and exclude rules on repo-relative paths, see `parsing::PathFilter`.
+/// Also accepts the former `{"mode": "include" | "exclude", "paths": [regex, ...]}` shape.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(try_from = "RawFilterConfig")]
 pub struct FilterConfig {
-    pub mode: String, // "include" or "exclude"
-    pub paths: Vec<String>,
+    pub rules: Vec<FilterRule>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct FilterRule {
+    pub mode: FilterMode,
+    pub pattern: String,
+    #[serde(default)]
+    pub syntax: PatternSyntax,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FilterMode {
+    Include,
+    Exclude,
+}
+
+impl std::str::FromStr for FilterMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "include" => Ok(Self::Include),
+            "exclude" => Ok(Self::Exclude),
+            _ => Err(format!("Unknown filter mode {s}, expected include or exclude")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PatternSyntax {
+    /// gitignore-style: `services/**`, `**/generated/**`, `*_test.go`
+    #[default]
+    Glob,
+    Regex,
+}
+
+#[derive(Deserialize)]
+struct RawFilterConfig {
+    #[serde(default)]
+    rules: Vec<FilterRule>,
+    mode: Option<String>,
+    #[serde(default)]
+    paths: Vec<String>,
 }
 
-#[derive(Encode, Decode, Deserialize, Serialize, Debug, Clone)]
+impl TryFrom<RawFilterConfig> for FilterConfig {
+    type Error = String;
+
+    fn try_from(raw: RawFilterConfig) -> Result<Self, Self::Error> {
+        let mut rules = raw.rules;
+        match raw.mode {
+            Some(mode) => {
+                let mode = mode.parse()?;
+                rules.extend(raw.paths.into_iter().map(|pattern| FilterRule {
+                    mode,
+                    pattern,
+                    syntax: PatternSyntax::Regex,
+                }));
+            }
+            None if !raw.paths.is_empty() => {
+                return Err("Filter paths need a mode, include or exclude".to_string());
+            }
+            None => (),
+        }
+        Ok(Self { rules })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScanMode {
+    /// Drop the collection and re-embed every file
+    #[default]
+    Full,
+    /// Only re-embed added or changed files, and drop points of removed ones
+    Incremental,
+}
+
+#[derive(Encode, Decode, Deserialize, Serialize, Debug, Clone, Default)]
 pub struct Chunk {
     pub path: String,
     pub chunk_index: String,
@@ -17,9 +97,27 @@ pub struct Chunk {
     pub text: String,
     pub function_name: Option<String>,
     pub chunk_type: ChunkType,
+    pub heading_path: Vec<String>, // enclosing headings of a doc chunk, outermost first
+    pub file_hash: String,
+    pub content_hash: String, // sha256 of the whitespace-normalized text, before the header
+    pub duplicates: Vec<ChunkLocation>, // other places holding the same text, embedded once
+    pub point_id: String, // UUID of its Qdrant point, stable across scans, see `utils::assign_point_ids`
+    pub linked: Vec<ChunkLocation>, // declarations in headers ↔ definitions, see `utils::link_declarations`
+    pub attributes: Vec<String>, // C# attributes of the definition and of its enclosing types, e.g. `HttpGet("{id}")`
+    pub parent_id: String, // point id of the enclosing class / impl chunk, a skeleton; its chunk_index until `utils::assign_point_ids`
+}
+
+/// Where else the text of a chunk appears (see `utils::dedupe_chunks`), or where the code
+/// it declares or defines lives.
+#[derive(Encode, Decode, Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct ChunkLocation {
+    pub path: String,
+    pub chunk_start_line: usize,
+    pub chunk_end_line: usize,
+    pub file_hash: String,
 }
 
-#[derive(Encode, Decode, Deserialize, Serialize, Debug, Clone)]
+#[derive(Encode, Decode, Deserialize, Serialize, Debug, Clone, Default)]
 pub enum ChunkType {
     Function,
     Class,
@@ -27,12 +125,45 @@ pub enum ChunkType {
     Interface,
     Struct,
     Impl,
+    Constant, // const / var declarations
+    Declaration, // function prototype of a C / C++ header
+    Property, // C# property
+    Module, // Rust or Ruby module
+    Trait, // Rust or PHP trait
+    Enum,
+    Macro, // macro_rules!
+    TypeAlias,
+    #[default]
     LineChunk,
+    Doc,
+}
+
+/// Which chunks a question is answered from.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChunkScope {
+    #[default]
+    All,
+    Code,
+    Docs,
+}
+
+impl std::str::FromStr for ChunkScope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "all" => Ok(Self::All),
+            "code" => Ok(Self::Code),
+            "docs" => Ok(Self::Docs),
+            _ => Err(format!("Unknown scope {s}, expected all, code or docs")),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct Embedding {
-    pub id: String, // UUID
+    pub id: String, // UUID, the `point_id` of the chunk
     pub chunk: Chunk,
     pub vector: Vec<f32>,
 }
@@ -43,9 +174,96 @@ impl Embedding {
         vector: Vec<f32>,
     ) -> Self {
         Self {
-            id: Uuid::new_v4().to_string(),
-            chunk : chunk,
+            id: chunk.point_id.clone(),
+            chunk,
             vector,
         }
     }
-}
\ No newline at end of file
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct SkippedFile {
+    pub path: String,
+    pub reason: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct SkippedChunk {
+    pub path: String,
+    pub chunk_index: String,
+    pub reason: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct FailedBatch {
+    pub index: usize,
+    pub chunks: usize,
+    pub error: String,
+}
+
+/// Summary of a `scan_repo` run, returned by the API and the CLI.
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct ScanReport {
+    pub repo: String,
+    pub collection: String,
+    pub git_ref: Option<String>,
+    pub commit: Option<String>, // revision indexed, when the repo is a git repository
+    pub files_seen: usize,
+    pub files_indexed: usize,
+    pub files_skipped: Vec<SkippedFile>,
+    pub chunks_per_language: BTreeMap<String, usize>,
+    pub chunks_per_type: BTreeMap<String, usize>,
+    pub chunks_embedded: usize,
+    pub chunks_deduplicated: usize, // identical to another chunk, stored as one of its locations
+    pub chunks_skipped: Vec<SkippedChunk>,
+    pub secrets_redacted: BTreeMap<String, usize>, // by kind, see `redaction::Redactor`
+    pub failed_batches: Vec<FailedBatch>,
+    pub estimated_tokens: usize,
+    pub actual_tokens: usize, // as reported by the embedding provider
+    pub duration_secs: f64,
+}
+
+impl ScanReport {
+    pub fn summary(&self) -> String {
+        format!(
+            "{} files seen, {} indexed, {} skipped, {} chunks embedded, {} deduplicated, {} chunks skipped, {} secrets redacted, {} failed batches, {} tokens in {:.1}s",
+            self.files_seen,
+            self.files_indexed,
+            self.files_skipped.len(),
+            self.chunks_embedded,
+            self.chunks_deduplicated,
+            self.chunks_skipped.len(),
+            self.secrets_redacted.values().sum::<usize>(),
+            self.failed_batches.len(),
+            self.actual_tokens,
+            self.duration_secs
+        )
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct CostEstimate {
+    pub provider: String,
+    pub model: String,
+    pub usd_per_million_tokens: Option<f64>, // None: model missing from the price list
+    pub cost_usd: Option<f64>,
+    pub configured: bool, // the model selected by EMBEDDING_PROVIDER / *_EMBEDDING_MODEL
+}
+
+/// What a scan would embed and cost, computed without Qdrant nor any embedding API.
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct ScanEstimate {
+    pub repo: String,
+    pub collection: String,
+    pub git_ref: Option<String>,
+    pub commit: Option<String>,
+    pub files: usize,
+    pub files_skipped: Vec<SkippedFile>,
+    pub chunks: usize,
+    pub chunks_deduplicated: usize,
+    pub chunks_per_language: BTreeMap<String, usize>,
+    pub chunks_per_type: BTreeMap<String, usize>,
+    pub secrets_redacted: BTreeMap<String, usize>,
+    pub tokens: usize,
+    pub costs: Vec<CostEstimate>,
+}
@@ -1,4 +1,8 @@
-use crate::types::Chunk;
+use crate::parsing;
+use crate::types::{Chunk, ChunkLocation, ChunkType, CostEstimate};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::Path;
+use sha2::{Digest, Sha256};
 use tiktoken_rs::cl100k_base;
 
 const MISTRAL_EMBEDDING_PRICE_PER_MILLION_TOKEN: f64 = 0.15;
@@ -13,47 +17,85 @@ const MAX_BATCH_SIZE: usize = 128;
 const MAX_TOTAL_TOKENS: usize = 16384;
 pub const MAX_SEQUENCE_LENGTH: usize = 8192;
 
-pub fn calculate_cost(all_chunks: &[Chunk]) {
+/// (provider, model, price per million tokens) of the supported embedding models.
+const EMBEDDING_PRICES: &[(&str, &str, f64)] = &[
+    ("mistral", "codestral-embed", MISTRAL_EMBEDDING_PRICE_PER_MILLION_TOKEN),
+    ("openai", "text-embedding-3-small", OPENAI_TEXT_EMBEDDING_3_SMALL_PRICE_PER_MILLION_TOKEN),
+    ("openai", "text-embedding-3-large", OPENAI_TEXT_EMBEDDING_3_LARGE_PRICE_PER_MILLION_TOKEN),
+];
+
+/// Embedding provider and model selected by the environment, as used by `create_embedder`.
+pub fn configured_embedding_model() -> (String, String) {
+    let provider = std::env::var("EMBEDDING_PROVIDER")
+        .unwrap_or_else(|_| "mistral".to_string())
+        .to_lowercase();
+
+    let model = match provider.as_str() {
+        "openai" => std::env::var("OPENAI_EMBEDDING_MODEL")
+            .unwrap_or_else(|_| "text-embedding-3-small".to_string()),
+        _ => std::env::var("MISTRAL_EMBEDDING_MODEL")
+            .unwrap_or_else(|_| "codestral-embed".to_string()),
+    };
+    let provider = if provider == "openai" { provider } else { "mistral".to_string() };
+    (provider, model)
+}
+
+pub fn count_tokens(all_chunks: &[Chunk]) -> usize {
     let bpe = cl100k_base().unwrap();
 
-    let total_tokens: usize = all_chunks
+    all_chunks
         .iter()
         .map(|chunk| bpe.encode_with_special_tokens(&chunk.text).len())
-        .sum();
+        .sum()
+}
 
-    let provider = std::env::var("EMBEDDING_PROVIDER")
-        .unwrap_or_else(|_| "mistral".to_string())
-        .to_lowercase();
+/// Cost of embedding `tokens` with every known model; the configured one is flagged.
+pub fn estimate_costs(tokens: usize) -> Vec<CostEstimate> {
+    let (provider, model) = configured_embedding_model();
 
-    match provider.as_str() {
-        "openai" => {
-            let model = std::env::var("OPENAI_EMBEDDING_MODEL")
-                .unwrap_or_else(|_| "text-embedding-3-small".to_string());
-            
-            let price_per_million = match model.as_str() {
-                "text-embedding-3-large" => OPENAI_TEXT_EMBEDDING_3_LARGE_PRICE_PER_MILLION_TOKEN,
-                "text-embedding-3-small" | _ => OPENAI_TEXT_EMBEDDING_3_SMALL_PRICE_PER_MILLION_TOKEN,
-            };
-            
-            let total_cost = (total_tokens as f64 / 1_000_000.0) * price_per_million;
-            
-            println!(
-                "Total tokens: {}\nEstimated embedding cost with {}: ${:.2}",
-                total_tokens, model, total_cost
-            );
-        }
-        "mistral" | _ => {
-            let total_cost = (total_tokens as f64 / 1_000_000.0) * MISTRAL_EMBEDDING_PRICE_PER_MILLION_TOKEN;
-            
-            println!(
-                "Total tokens: {}\nEstimated embedding cost: ${:.2}",
-                total_tokens, total_cost
-            );
-        }
+    let mut costs: Vec<CostEstimate> = EMBEDDING_PRICES
+        .iter()
+        .map(|(p, m, price)| CostEstimate {
+            provider: p.to_string(),
+            model: m.to_string(),
+            usd_per_million_tokens: Some(*price),
+            cost_usd: Some((tokens as f64 / 1_000_000.0) * price),
+            configured: *p == provider && *m == model,
+        })
+        .collect();
+
+    // Modèle configuré absent de la grille : on le signale sans prix
+    if !costs.iter().any(|c| c.configured) {
+        costs.push(CostEstimate {
+            provider,
+            model,
+            usd_per_million_tokens: None,
+            cost_usd: None,
+            configured: true,
+        });
     }
+    costs
 }
 
-pub fn calculate_ask_cost(input: &String) {
+/// Print the estimated embedding cost and return the estimated token count.
+pub fn calculate_cost(all_chunks: &[Chunk]) -> usize {
+    let total_tokens = count_tokens(all_chunks);
+
+    if let Some(cost) = estimate_costs(total_tokens).into_iter().find(|c| c.configured) {
+        let price = match cost.cost_usd {
+            Some(usd) => format!("${usd:.2}"),
+            None => "unknown".to_string(),
+        };
+        println!(
+            "Total tokens: {}\nEstimated embedding cost with {}: {}",
+            total_tokens, cost.model, price
+        );
+    }
+
+    total_tokens
+}
+
+pub fn calculate_ask_cost(input: &str) {
     let bpe = cl100k_base().unwrap();
     let total_tokens = bpe.encode_with_special_tokens(input).len();
 
@@ -69,7 +111,7 @@ pub fn calculate_ask_cost(input: &String) {
                 total_tokens, total_cost
             );
         }
-        "mistral" | _ => {
+        _ => {
             let total_cost_standard =
                 (total_tokens as f64 / 1_000_000.0) * MISTRAL_COMPLETION_PRICE_PER_MILLION_TOKEN;
             let total_cost_premium =
@@ -102,24 +144,15 @@ pub fn make_batches(chunks: Vec<Chunk>) -> Vec<Vec<Chunk>> {
             chunk.text.clone()
         };
         let n_tokens = enc.encode_ordinary(&t).len();
-        if current_batch.len() >= MAX_BATCH_SIZE
-            || current_batch_tokens + n_tokens > MAX_TOTAL_TOKENS
+        if (current_batch.len() >= MAX_BATCH_SIZE
+            || current_batch_tokens + n_tokens > MAX_TOTAL_TOKENS)
+            && !current_batch.is_empty()
         {
-            if !current_batch.is_empty() {
-                batches.push(current_batch);
-                current_batch = Vec::new();
-                current_batch_tokens = 0;
-            }
+            batches.push(current_batch);
+            current_batch = Vec::new();
+            current_batch_tokens = 0;
         }
-        let prepared_chunk = Chunk {
-            path: chunk.path,
-            chunk_end_line: chunk.chunk_end_line,
-            chunk_index: chunk.chunk_index,
-            chunk_start_line: chunk.chunk_start_line,
-            text: t,
-            function_name: chunk.function_name.clone(),
-            chunk_type: chunk.chunk_type.clone(),
-        };
+        let prepared_chunk = Chunk { text: t, ..chunk };
         current_batch.push(prepared_chunk);
         current_batch_tokens += n_tokens;
     }
@@ -134,13 +167,384 @@ pub fn prepare_chunk(mut chunk: Chunk) -> Chunk {
         .strip_prefix("clone/")
         .unwrap_or(&chunk.path);
     
-    let name = chunk.function_name
-        .as_deref()
-        .unwrap_or("anonymous");
-    
-    chunk.text = format!("[{:?}] {} @ {}:{}-{}\n{}", 
+    let heading_path = chunk.heading_path.join(" > ");
+    let name = match chunk.function_name.as_deref() {
+        Some(name) => name,
+        None if !heading_path.is_empty() => &heading_path,
+        None => "anonymous",
+    };
+
+    // Les attributs de la classe (e.g. `[Authorize]`) ne sont pas dans le texte de ses méthodes
+    let attributes = if chunk.attributes.is_empty() {
+        String::new()
+    } else {
+        format!(" [{}]", chunk.attributes.join(", "))
+    };
+
+    chunk.text = format!("[{:?}] {} @ {}:{}-{}{}\n{}", 
         chunk.chunk_type, name, short_path, 
-        chunk.chunk_start_line, chunk.chunk_end_line, 
+        chunk.chunk_start_line, chunk.chunk_end_line, attributes,
         chunk.text.trim());
     chunk
-}
\ No newline at end of file
+}
+
+/// Give the chunks of one file their Qdrant point id, a name-based UUID (SHA-256, version 8)
+/// of the repo, the path in the repo and the place of the chunk in the code structure: its type
+/// and name (or headings) with the rank among namesakes, so that editing another function
+/// doesn't change it. Unnamed line chunks fall back on their position in the file.
+/// Re-indexing a chunk then overwrites its point instead of adding one.
+/// The `parent_id` of a chunk, the chunk_index of its parent, becomes the point id of the parent.
+pub fn assign_point_ids(chunks: &mut [Chunk]) {
+    let mut namesakes: HashMap<String, usize> = HashMap::new();
+    for chunk in chunks.iter_mut() {
+        // Suffixe des sous-chunks d'un bloc trop long : "3-1" → "1"
+        let part = chunk.chunk_index.split_once('-').map_or("", |(_, part)| part);
+        let name = match (&chunk.function_name, chunk.heading_path.is_empty()) {
+            (Some(name), _) => name.clone(),
+            (None, false) => chunk.heading_path.join(" > "),
+            (None, true) => format!("#{}", chunk.chunk_index),
+        };
+        let key = format!("{:?}:{name}:{part}", chunk.chunk_type);
+        let rank = namesakes.entry(key.clone()).or_default();
+        let identity = format!("{}#{key}:{rank}", chunk.path.strip_prefix("clone/").unwrap_or(&chunk.path));
+        *rank += 1;
+
+        let digest = Sha256::digest(identity.as_bytes());
+        let bytes: [u8; 16] = digest[..16].try_into().unwrap();
+        chunk.point_id = uuid::Builder::from_custom_bytes(bytes).into_uuid().to_string();
+    }
+
+    // Un parent découpé en sous-chunks est désigné par le premier
+    let mut point_ids: HashMap<String, String> = HashMap::new();
+    for chunk in chunks.iter() {
+        let index = chunk.chunk_index.split('-').next().unwrap_or_default();
+        point_ids.entry(index.to_string()).or_insert_with(|| chunk.point_id.clone());
+    }
+    for chunk in chunks.iter_mut().filter(|chunk| !chunk.parent_id.is_empty()) {
+        chunk.parent_id = point_ids.get(&chunk.parent_id).cloned().unwrap_or_default();
+    }
+}
+
+/// Lines listing the other locations of a deduplicated chunk and the declarations or
+/// definitions linked to it, for the prompt context; empty when it has none.
+pub fn context_note(chunk: &Chunk) -> String {
+    let list = |locations: &[ChunkLocation]| {
+        locations
+            .iter()
+            .map(|location| {
+                let short_path = location.path.strip_prefix("clone/").unwrap_or(&location.path);
+                format!("{short_path}:{}-{}", location.chunk_start_line, location.chunk_end_line)
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let mut note = String::new();
+    if !chunk.duplicates.is_empty() {
+        note.push_str(&format!("(same code in {})\n", list(&chunk.duplicates)));
+    }
+    if !chunk.linked.is_empty() {
+        let relation = if parsing::is_header(Path::new(&chunk.path)) { "defined" } else { "declared" };
+        note.push_str(&format!("({relation} in {})\n", list(&chunk.linked)));
+    }
+    note
+}
+
+/// Skeleton of the class / impl enclosing `chunk`, to put before it in the prompt context,
+/// the first time one of its chunks is `shown`; empty otherwise.
+pub fn parent_context(chunk: &Chunk, parents: &HashMap<String, Chunk>, shown: &mut HashSet<String>) -> String {
+    match parents.get(&chunk.parent_id) {
+        Some(parent) if shown.insert(parent.point_id.clone()) => format!("{}\n", parent.text),
+        _ => String::new(),
+    }
+}
+
+// Seule la première partie d'un bloc découpé est liée
+fn is_first_part(chunk: &Chunk) -> bool {
+    chunk.chunk_index.split_once('-').is_none_or(|(_, part)| part == "0")
+}
+
+fn last_segment(name: &str) -> &str {
+    name.rsplit("::").next().unwrap_or(name)
+}
+
+/// Header chunk another file can define: a prototype, or a class whose methods are defined out of line.
+fn is_declaration(chunk: &Chunk) -> bool {
+    parsing::is_header(Path::new(&chunk.path))
+        && is_first_part(chunk)
+        && matches!(chunk.chunk_type, ChunkType::Declaration | ChunkType::Class | ChunkType::Struct)
+}
+
+fn is_definition(chunk: &Chunk) -> bool {
+    !parsing::is_header(Path::new(&chunk.path))
+        && is_first_part(chunk)
+        && matches!(chunk.chunk_type, ChunkType::Function | ChunkType::Method)
+}
+
+/// Unqualified names under which `link_declarations` may link `chunks` to other chunks:
+/// those of the declarations, of the definitions, and of the classes of methods.
+pub fn link_names(chunks: &[Chunk]) -> Vec<String> {
+    let mut names = BTreeSet::new();
+    for chunk in chunks {
+        let Some(name) = &chunk.function_name else { continue };
+        if is_declaration(chunk) || is_definition(chunk) {
+            names.insert(last_segment(name).to_string());
+        }
+        if let Some((owner, _)) = name.rsplit_once("::").filter(|_| is_definition(chunk)) {
+            names.insert(last_segment(owner).to_string());
+        }
+    }
+    names.into_iter().collect()
+}
+
+/// Link the declarations of C / C++ headers to the definitions of the same name in other
+/// files: a prototype to its function, a class to its methods defined out of line. Both sides
+/// record the location of the other in `linked`, once. Names match when one qualifies the other,
+/// e.g. `Server::start` defined after `using namespace net` and the `net::Server` class.
+pub fn link_declarations(chunks: &mut [Chunk]) {
+    let mut declarations: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (index, chunk) in chunks.iter().enumerate() {
+        if let Some(name) = chunk.function_name.as_deref().filter(|_| is_declaration(chunk)) {
+            declarations.entry(last_segment(name)).or_default().push(index);
+        }
+    }
+    if declarations.is_empty() {
+        return;
+    }
+
+    let same_name = |a: &str, b: &str| a == b || a.ends_with(&format!("::{b}")) || b.ends_with(&format!("::{a}"));
+    let mut links = Vec::new();
+    for (index, chunk) in chunks.iter().enumerate() {
+        let Some(name) = chunk.function_name.as_deref().filter(|_| is_definition(chunk)) else {
+            continue;
+        };
+        // Le prototype de la fonction, ou la classe d'une méthode définie hors de sa classe
+        let owner = name.rsplit_once("::").map(|(owner, _)| owner);
+        for (key, of_type) in [(Some(name), false), (owner, true)] {
+            let Some(key) = key else { continue };
+            for &declaration in declarations.get(last_segment(key)).into_iter().flatten() {
+                let other = &chunks[declaration];
+                let other_name = other.function_name.as_deref().unwrap_or_default();
+                if matches!(other.chunk_type, ChunkType::Class | ChunkType::Struct) == of_type
+                    && same_name(other_name, key)
+                    && other.path != chunk.path
+                {
+                    links.push((index, declaration));
+                }
+            }
+        }
+    }
+
+    let location = |chunk: &Chunk| ChunkLocation {
+        path: chunk.path.clone(),
+        chunk_start_line: chunk.chunk_start_line,
+        chunk_end_line: chunk.chunk_end_line,
+        file_hash: chunk.file_hash.clone(),
+    };
+    for (definition, declaration) in links {
+        let (definition_location, declaration_location) = (location(&chunks[definition]), location(&chunks[declaration]));
+        // Des chunks déjà indexés peuvent être liés depuis un scan précédent
+        if !chunks[definition].linked.contains(&declaration_location) {
+            chunks[definition].linked.push(declaration_location);
+        }
+        if !chunks[declaration].linked.contains(&definition_location) {
+            chunks[declaration].linked.push(definition_location);
+        }
+    }
+}
+
+/// Chunks rejected here are never sent to the embedding API.
+pub fn is_embeddable(text: &str) -> bool {
+    !text.trim().is_empty() && text.len() <= MAX_SEQUENCE_LENGTH
+}
+
+pub fn hash_content(content: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(content))
+}
+
+/// Hash of `text` ignoring indentation and line breaks, so that copies reindented or
+/// reformatted by another project still share it.
+pub fn normalized_hash(text: &str) -> String {
+    let normalized = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    hash_content(normalized.as_bytes())
+}
+
+/// Merge the chunks sharing a `content_hash` (vendored or copied code): the first one by
+/// path and line is kept, and the others become its `duplicates`, so the text is embedded once.
+/// Chunks keep the order of their first occurrence, and the children of a merged skeleton get
+/// the kept one as parent. Returns the number of chunks merged.
+pub fn dedupe_chunks(chunks: Vec<Chunk>) -> (Vec<Chunk>, usize) {
+    let mut groups: Vec<Vec<Chunk>> = Vec::new();
+    let mut group_of: HashMap<String, usize> = HashMap::new();
+    for chunk in chunks {
+        // Chunks d'un ancien fichier de chunks, sans hash : gardés tels quels
+        if chunk.content_hash.is_empty() {
+            groups.push(vec![chunk]);
+            continue;
+        }
+        match group_of.get(&chunk.content_hash) {
+            Some(&group) => groups[group].push(chunk),
+            None => {
+                group_of.insert(chunk.content_hash.clone(), groups.len());
+                groups.push(vec![chunk]);
+            }
+        }
+    }
+
+    let mut merged = 0;
+    // Point d'une copie supprimée → point gardé, pour les enfants d'un squelette fusionné
+    let mut kept_points: HashMap<String, String> = HashMap::new();
+    let mut deduped: Vec<Chunk> = groups
+        .into_iter()
+        .map(|mut group| {
+            group.sort_by(|a, b| (&a.path, a.chunk_start_line).cmp(&(&b.path, b.chunk_start_line)));
+            let mut copies = group.into_iter();
+            let mut chunk = copies.next().unwrap();
+            for copy in copies {
+                merged += 1;
+                if !copy.point_id.is_empty() {
+                    kept_points.insert(copy.point_id, chunk.point_id.clone());
+                }
+                chunk.duplicates.push(ChunkLocation {
+                    path: copy.path,
+                    chunk_start_line: copy.chunk_start_line,
+                    chunk_end_line: copy.chunk_end_line,
+                    file_hash: copy.file_hash,
+                });
+            }
+            chunk
+        })
+        .collect();
+    for chunk in deduped.iter_mut() {
+        if let Some(kept) = kept_points.get(&chunk.parent_id) {
+            chunk.parent_id = kept.clone();
+        }
+    }
+    (deduped, merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_dedupe_chunks_keeps_one_point_per_text() {
+        let chunk = |path: &str, line: usize, text: &str| Chunk {
+            path: path.to_string(),
+            chunk_index: line.to_string(),
+            chunk_start_line: line,
+            chunk_end_line: line + 2,
+            text: text.to_string(),
+            chunk_type: ChunkType::Function,
+            file_hash: format!("hash of {path}"),
+            content_hash: normalized_hash(text),
+            ..Default::default()
+        };
+        let chunks = vec![
+            chunk("clone/app/vendor/lib.rs", 10, "fn add(a: i32, b: i32) -> i32 {\n    a + b\n}"),
+            chunk("clone/app/src/main.rs", 1, "fn main() {}"),
+            chunk("clone/app/src/lib.rs", 4, "fn add(a: i32, b: i32) -> i32 {\n\ta + b\n}\n"),
+        ];
+
+        let (chunks, merged) = dedupe_chunks(chunks);
+        assert_eq!(merged, 1);
+        let paths: Vec<_> = chunks.iter().map(|c| c.path.as_str()).collect();
+        assert_eq!(paths, vec!["clone/app/src/lib.rs", "clone/app/src/main.rs"]);
+        assert_eq!(
+            chunks[0].duplicates,
+            vec![ChunkLocation {
+                path: "clone/app/vendor/lib.rs".to_string(),
+                chunk_start_line: 10,
+                chunk_end_line: 12,
+                file_hash: "hash of clone/app/vendor/lib.rs".to_string(),
+            }]
+        );
+        assert_eq!(context_note(&chunks[0]), "(same code in app/vendor/lib.rs:10-12)\n");
+
+        // Le squelette d'une copie disparaît : ses méthodes pointent vers celui gardé
+        let skeleton = |path: &str, point_id: &str| Chunk {
+            point_id: point_id.to_string(),
+            ..chunk(path, 1, "impl Point { … }")
+        };
+        let method = |path: &str, parent_id: &str, text: &str| Chunk {
+            parent_id: parent_id.to_string(),
+            ..chunk(path, 2, text)
+        };
+        let (chunks, _) = dedupe_chunks(vec![
+            skeleton("clone/app/vendor/point.rs", "vendored"),
+            method("clone/app/vendor/point.rs", "vendored", "fn x() -> i32 { 1 }"),
+            skeleton("clone/app/src/point.rs", "own"),
+            method("clone/app/src/point.rs", "own", "fn x() -> i32 { 2 }"),
+        ]);
+        let parents: Vec<_> = chunks.iter().map(|c| c.parent_id.as_str()).collect();
+        assert_eq!(parents, vec!["", "own", "own"]);
+    }
+
+    #[test]
+    fn test_point_ids_follow_the_code_structure() {
+        let chunk = |index: usize, name: Option<&str>| Chunk {
+            path: "clone/app/src/lib.rs".to_string(),
+            chunk_index: index.to_string(),
+            chunk_start_line: index * 10,
+            chunk_end_line: index * 10 + 5,
+            text: String::new(),
+            function_name: name.map(str::to_string),
+            chunk_type: if name.is_some() { ChunkType::Function } else { ChunkType::LineChunk },
+            ..Default::default()
+        };
+        let ids = |mut chunks: Vec<Chunk>| {
+            assign_point_ids(&mut chunks);
+            chunks.into_iter().map(|c| c.point_id).collect::<Vec<_>>()
+        };
+
+        let before = ids(vec![chunk(0, Some("new")), chunk(1, Some("fmt")), chunk(2, Some("fmt"))]);
+        let after = ids(vec![chunk(0, Some("parse")), chunk(1, Some("new")), chunk(2, Some("fmt")), chunk(3, Some("fmt"))]);
+        assert_eq!(before, after[1..]);
+        assert_ne!(before[1], before[2], "namesakes get distinct ids");
+        assert!(Uuid::parse_str(&before[0]).is_ok());
+        assert_ne!(ids(vec![chunk(0, None)]), ids(vec![chunk(1, None)]));
+
+        // Le parent découpé est désigné par son premier sous-chunk
+        let mut chunks = vec![chunk(0, Some("Cache")), chunk(0, Some("Cache")), chunk(1, Some("get"))];
+        chunks[0].chunk_index = "0-0".to_string();
+        chunks[1].chunk_index = "0-1".to_string();
+        chunks[2].parent_id = "0".to_string();
+        assign_point_ids(&mut chunks);
+        assert_eq!(chunks[2].parent_id, chunks[0].point_id);
+    }
+
+    #[test]
+    fn test_link_declarations_across_files() {
+        let chunk = |path: &str, name: &str, chunk_type: ChunkType, line: usize| Chunk {
+            path: path.to_string(),
+            chunk_index: line.to_string(),
+            chunk_start_line: line,
+            chunk_end_line: line + 1,
+            text: String::new(),
+            function_name: Some(name.to_string()),
+            chunk_type,
+            ..Default::default()
+        };
+        let mut chunks = vec![
+            chunk("clone/app/include/server.hpp", "net::parse_port", ChunkType::Declaration, 4),
+            chunk("clone/app/include/server.hpp", "net::Server", ChunkType::Class, 7),
+            chunk("clone/app/src/server.cc", "Server::start", ChunkType::Method, 3),
+            chunk("clone/app/src/port.cc", "net::parse_port", ChunkType::Function, 1),
+            chunk("clone/app/src/main.cc", "main", ChunkType::Function, 1),
+        ];
+        link_declarations(&mut chunks);
+
+        let linked = |chunk: &Chunk| chunk.linked.iter().map(|l| l.path.clone()).collect::<Vec<_>>();
+        assert_eq!(linked(&chunks[0]), vec!["clone/app/src/port.cc"]);
+        assert_eq!(linked(&chunks[1]), vec!["clone/app/src/server.cc"]);
+        assert_eq!(linked(&chunks[2]), vec!["clone/app/include/server.hpp"]);
+        assert!(chunks[4].linked.is_empty());
+        assert_eq!(context_note(&chunks[2]), "(declared in app/include/server.hpp:7-8)\n");
+        assert_eq!(context_note(&chunks[1]), "(defined in app/src/server.cc:3-4)\n");
+
+        // Relier de nouveau des chunks déjà indexés n'ajoute rien
+        link_declarations(&mut chunks);
+        assert_eq!(linked(&chunks[0]), vec!["clone/app/src/port.cc"]);
+        assert_eq!(link_names(&chunks[2..3]), vec!["Server", "start"]);
+    }
+}
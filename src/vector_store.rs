@@ -1,17 +1,20 @@
-use crate::types::{Chunk, Embedding};
+use crate::types::{Chunk, ChunkLocation, ChunkScope, Embedding, SkippedChunk};
 use qdrant_client::Qdrant;
 use qdrant_client::config::QdrantConfig;
 use qdrant_client::qdrant::{
-    CreateCollectionBuilder, Distance, PointStruct, SearchPointsBuilder, UpsertPointsBuilder,
-    VectorParamsBuilder, PayloadSchemaType, CreateFieldIndexCollectionBuilder, FieldType,
+    Condition, CountPointsBuilder, CreateCollectionBuilder, DeletePointsBuilder, Distance, Filter, GetPointsBuilder, ListValue,
+    PayloadIncludeSelector, PointId, PointStruct, ScrollPointsBuilder, SearchPointsBuilder, SetPayloadPointsBuilder, UpsertPointsBuilder,
+    VectorParamsBuilder,
+    PayloadSchemaType, CreateFieldIndexCollectionBuilder, FieldType, vectors_config, point_id::PointIdOptions,
 };
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::iter::Iterator;
 
 pub struct VectorStore {
     client: Qdrant,
     collection_name: String,
     vector_dim: usize,
+    warnings: Vec<String>, // differences between the config of the index and the current one
 }
 
 impl VectorStore {
@@ -27,11 +30,50 @@ impl VectorStore {
             client
                 .delete_collection(collection_name)
                 .await
-                .expect("Error trying to reset collection");
-            
+                .map_err(|e| format!("Error trying to reset collection: {e}"))?;
+
             // Wait for the collection to be deleted
             tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
         }
+        Self::create_collection(&client, collection_name, vector_dim).await?;
+
+        Ok(Self {
+            client,
+            collection_name: collection_name.to_string(),
+            vector_dim,
+            warnings: Vec::new(),
+        })
+    }
+
+    /// Open the collection, creating it if needed, without touching existing points.
+    pub async fn open_or_create(collection_name: &str, vector_dim: usize) -> Result<Self, String> {
+        let config = QdrantConfig::from_url("http://qdrant:6334");
+        let client = Qdrant::new(config).map_err(|e| e.to_string())?;
+
+        let exists = client
+            .collection_exists(collection_name)
+            .await
+            .map_err(|e| e.to_string())?;
+        if exists {
+            // Une collection créée avant l'ajout d'un index ne l'a pas
+            Self::create_indexes(&client, collection_name).await?;
+        } else {
+            Self::create_collection(&client, collection_name, vector_dim).await?;
+        }
+
+        Ok(Self {
+            client,
+            collection_name: collection_name.to_string(),
+            vector_dim,
+            warnings: Vec::new(),
+        })
+    }
+
+    async fn create_collection(
+        client: &Qdrant,
+        collection_name: &str,
+        vector_dim: usize,
+    ) -> Result<(), String> {
         client
             .create_collection(
                 CreateCollectionBuilder::new(collection_name).vectors_config(
@@ -41,6 +83,11 @@ impl VectorStore {
             .await
             .map_err(|e| e.to_string())?;
 
+        Self::create_indexes(client, collection_name).await
+    }
+
+    /// Payload indexes of the collection; creating an existing one is a no-op.
+    async fn create_indexes(client: &Qdrant, collection_name: &str) -> Result<(), String> {
         // Créer un index sur le champ chunk_text pour accélérer la recherche textuelle
         client
             .create_field_index(
@@ -53,11 +100,43 @@ impl VectorStore {
             .await
             .map_err(|e| e.to_string())?;
 
-        Ok(Self {
-            client,
-            collection_name: collection_name.to_string(),
-            vector_dim,
-        })
+        // Index keyword sur path pour supprimer rapidement les points d'un fichier
+        client
+            .create_field_index(
+                CreateFieldIndexCollectionBuilder::new(
+                    collection_name,
+                    "path",
+                    FieldType::Keyword
+                ),
+            )
+            .await
+            .map_err(|e| e.to_string())?;
+
+        // Idem pour les copies d'un chunk dédupliqué
+        client
+            .create_field_index(
+                CreateFieldIndexCollectionBuilder::new(
+                    collection_name,
+                    "duplicates[].path",
+                    FieldType::Keyword
+                ),
+            )
+            .await
+            .map_err(|e| e.to_string())?;
+
+        // Index keyword sur chunk_type pour filtrer code / documentation
+        client
+            .create_field_index(
+                CreateFieldIndexCollectionBuilder::new(
+                    collection_name,
+                    "chunk_type",
+                    FieldType::Keyword
+                ),
+            )
+            .await
+            .map_err(|e| e.to_string())?;
+
+        Ok(())
     }
 
     pub async fn try_open(collection_name: &str, vector_dim: usize) -> Result<Self, String> {
@@ -72,26 +151,73 @@ impl VectorStore {
             return Err(format!("No collection {} found", collection_name));
         }
 
+        let info = client
+            .collection_info(collection_name)
+            .await
+            .map_err(|e| e.to_string())?;
+        let indexed_dim = info
+            .result
+            .and_then(|info| info.config)
+            .and_then(|config| config.params)
+            .and_then(|params| params.vectors_config)
+            .and_then(|vectors| vectors.config)
+            .and_then(|config| match config {
+                vectors_config::Config::Params(params) => Some(params.size as usize),
+                vectors_config::Config::ParamsMap(_) => None,
+            });
+        if let Some(indexed_dim) = indexed_dim.filter(|dim| *dim != vector_dim) {
+            return Err(format!(
+                "{collection_name} holds vectors of dimension {indexed_dim} but VECTOR_DIMENSION is {vector_dim}: \
+                 re-scan it or restore the config it was built with"
+            ));
+        }
+
+        let warnings = crate::manifest::check_query(collection_name)?;
+        for warning in &warnings {
+            eprintln!("Warning: {warning}");
+        }
+
         Ok(Self {
             client,
             collection_name: collection_name.to_string(),
             vector_dim,
+            warnings,
         })
     }
 
+    /// Warnings raised when the collection was opened, e.g. a chunk size that changed since the scan.
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    /// Upsert the embeddings, returning the ones rejected because of a wrong vector dimension.
     pub async fn insert_many_embeddings_bulk(
         &self,
         embeddings: &[Embedding],
-    ) -> Result<(), String> {
-        let points: Vec<PointStruct> = embeddings
+    ) -> Result<Vec<SkippedChunk>, String> {
+        let (valid, invalid): (Vec<&Embedding>, Vec<&Embedding>) = embeddings
             .iter()
-            .filter(|e| {
-                let ok = e.vector.len() == self.vector_dim && !e.vector.is_empty();
-                if !ok {
-                    eprintln!("SKIP embedding id={} len={}", e.id, e.vector.len());
-                }
-                ok
+            .partition(|e| e.vector.len() == self.vector_dim && !e.vector.is_empty());
+
+        let skipped = invalid
+            .into_iter()
+            .map(|e| SkippedChunk {
+                path: e.chunk.path.clone(),
+                chunk_index: e.chunk.chunk_index.clone(),
+                reason: format!(
+                    "vector dimension {} instead of {}",
+                    e.vector.len(),
+                    self.vector_dim
+                ),
             })
+            .collect();
+
+        if valid.is_empty() {
+            return Ok(skipped);
+        }
+
+        let points: Vec<PointStruct> = valid
+            .into_iter()
             .map(|emb| {
                 let mut payload = HashMap::new();
                 payload.insert("path".to_string(), emb.chunk.path.clone().into());
@@ -119,6 +245,33 @@ impl VectorStore {
                     "chunk_type".to_string(),
                     format!("{:?}", emb.chunk.chunk_type).into(),
                 );
+                payload.insert(
+                    "heading_path".to_string(),
+                    qdrant_client::qdrant::Value {
+                        kind: Some(qdrant_client::qdrant::value::Kind::ListValue(ListValue {
+                            values: emb.chunk.heading_path.iter().map(|h| h.as_str().into()).collect(),
+                        })),
+                    },
+                );
+                payload.insert("file_hash".to_string(), emb.chunk.file_hash.clone().into());
+                payload.insert("content_hash".to_string(), emb.chunk.content_hash.clone().into());
+                payload.insert("parent_id".to_string(), emb.chunk.parent_id.clone().into());
+                payload.insert(
+                    "duplicates".to_string(),
+                    serde_json::to_value(&emb.chunk.duplicates).unwrap_or_default().into(),
+                );
+                payload.insert(
+                    "linked".to_string(),
+                    serde_json::to_value(&emb.chunk.linked).unwrap_or_default().into(),
+                );
+                payload.insert(
+                    "attributes".to_string(),
+                    qdrant_client::qdrant::Value {
+                        kind: Some(qdrant_client::qdrant::value::Kind::ListValue(ListValue {
+                            values: emb.chunk.attributes.iter().map(|a| a.as_str().into()).collect(),
+                        })),
+                    },
+                );
 
                 PointStruct::new(emb.id.clone(), emb.vector.clone(), payload)
             })
@@ -128,17 +281,206 @@ impl VectorStore {
             .upsert_points(UpsertPointsBuilder::new(&self.collection_name, points))
             .await
             .map_err(|e| e.to_string())?;
+        Ok(skipped)
+    }
+
+    pub async fn is_empty(&self) -> Result<bool, String> {
+        let resp = self
+            .client
+            .count(CountPointsBuilder::new(&self.collection_name).exact(false))
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(resp.result.is_none_or(|r| r.count == 0))
+    }
+
+    /// Content hash of every file currently stored in the collection (or only of `paths`
+    /// when given), keyed by path. Files only holding copies of deduplicated chunks included.
+    /// `None` when a point has no `file_hash`: the collection predates incremental scans.
+    pub async fn indexed_file_hashes(
+        &self,
+        paths: Option<&[String]>,
+    ) -> Result<Option<HashMap<String, String>>, String> {
+        let mut hashes = HashMap::new();
+        let mut offset = None;
+
+        loop {
+            let mut builder = ScrollPointsBuilder::new(&self.collection_name)
+                .with_payload(PayloadIncludeSelector {
+                    fields: vec!["path".to_string(), "file_hash".to_string(), "duplicates".to_string()],
+                })
+                .with_vectors(false)
+                .limit(1024);
+            if let Some(paths) = paths {
+                builder = builder.filter(Self::files_filter(paths));
+            }
+            if let Some(id) = offset {
+                builder = builder.offset(id);
+            }
+
+            let resp = self
+                .client
+                .scroll(builder)
+                .await
+                .map_err(|e| e.to_string())?;
+
+            for pt in resp.result {
+                match (
+                    Self::payload_string(&pt.payload, "path"),
+                    Self::payload_string(&pt.payload, "file_hash"),
+                ) {
+                    (Some(path), Some(hash)) => {
+                        hashes.insert(path, hash);
+                    }
+                    _ => return Ok(None),
+                }
+                for copy in Self::payload_locations(&pt.payload, "duplicates") {
+                    hashes.insert(copy.path, copy.file_hash);
+                }
+            }
+
+            match resp.next_page_offset {
+                Some(next) => offset = Some(next),
+                None => break,
+            }
+        }
+
+        Ok(Some(hashes))
+    }
+
+    /// Delete every point holding a chunk of one of the given files. Returns the other files
+    /// those points were shared with (copies of a deduplicated chunk), which must be re-indexed.
+    pub async fn delete_files(&self, paths: &[String]) -> Result<Vec<String>, String> {
+        if paths.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let deleted: HashSet<&String> = paths.iter().collect();
+        let mut orphans = BTreeSet::new();
+        let mut offset = None;
+        loop {
+            let mut builder = ScrollPointsBuilder::new(&self.collection_name)
+                .with_payload(PayloadIncludeSelector {
+                    fields: vec!["path".to_string(), "duplicates".to_string()],
+                })
+                .with_vectors(false)
+                .filter(Self::files_filter(paths))
+                .limit(1024);
+            if let Some(id) = offset {
+                builder = builder.offset(id);
+            }
+            let resp = self
+                .client
+                .scroll(builder)
+                .await
+                .map_err(|e| e.to_string())?;
+
+            for pt in resp.result {
+                let copies = Self::payload_locations(&pt.payload, "duplicates").into_iter().map(|copy| copy.path);
+                orphans.extend(
+                    Self::payload_string(&pt.payload, "path")
+                        .into_iter()
+                        .chain(copies)
+                        .filter(|path| !deleted.contains(path)),
+                );
+            }
+
+            match resp.next_page_offset {
+                Some(next) => offset = Some(next),
+                None => break,
+            }
+        }
+
+        self.client
+            .delete_points(
+                DeletePointsBuilder::new(&self.collection_name)
+                    .points(Self::files_filter(paths))
+                    .wait(true),
+            )
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(orphans.into_iter().collect())
+    }
+
+    /// Chunks whose name contains one of `names`, and the ones linked to one of `paths`: the
+    /// indexed chunks `utils::link_declarations` may have to link or unlink after an update.
+    pub async fn linkable_chunks(&self, names: &[String], paths: &[String]) -> Result<Vec<Chunk>, String> {
+        // Sans index texte sur function_name, matches_text cherche la sous-chaîne
+        let mut conditions: Vec<Condition> = names
+            .iter()
+            .map(|name| Condition::matches_text("function_name", name.clone()))
+            .collect();
+        conditions.push(Condition::matches("linked[].path", paths.to_vec()));
+
+        let mut chunks = Vec::new();
+        let mut offset = None;
+        loop {
+            let mut builder = ScrollPointsBuilder::new(&self.collection_name)
+                .with_payload(true)
+                .with_vectors(false)
+                .filter(Filter::should(conditions.clone()))
+                .limit(1024);
+            if let Some(id) = offset {
+                builder = builder.offset(id);
+            }
+            let resp = self
+                .client
+                .scroll(builder)
+                .await
+                .map_err(|e| e.to_string())?;
+
+            chunks.extend(
+                resp.result
+                    .iter()
+                    .filter_map(|pt| Self::extract_payload(pt.id.as_ref(), &pt.payload).ok()),
+            );
+
+            match resp.next_page_offset {
+                Some(next) => offset = Some(next),
+                None => break,
+            }
+        }
+        Ok(chunks)
+    }
+
+    /// Replace the `linked` payload of the points of `chunks`.
+    pub async fn set_linked(&self, chunks: &[Chunk]) -> Result<(), String> {
+        for chunk in chunks {
+            let payload = HashMap::from([(
+                "linked".to_string(),
+                serde_json::to_value(&chunk.linked).unwrap_or_default().into(),
+            )]);
+            self.client
+                .set_payload(
+                    SetPayloadPointsBuilder::new(&self.collection_name, payload)
+                        .points_selector(vec![PointId::from(chunk.point_id.clone())])
+                        .wait(true),
+                )
+                .await
+                .map_err(|e| e.to_string())?;
+        }
         Ok(())
     }
 
+    /// Points holding a chunk of one of `paths`, as the first location or as a copy.
+    fn files_filter(paths: &[String]) -> Filter {
+        Filter::should([
+            Condition::matches("path", paths.to_vec()),
+            Condition::matches("duplicates[].path", paths.to_vec()),
+        ])
+    }
+
     pub async fn search_top_k(
         &self,
         query_vector: &[f32],
         top_k: u64,
+        scope: ChunkScope,
     ) -> Result<Vec<Chunk>, String> {
-        let search_builder =
+        let mut search_builder =
             SearchPointsBuilder::new(&self.collection_name, query_vector.to_vec(), top_k)
                 .with_payload(true);
+        if let Some(filter) = Self::scope_filter(scope) {
+            search_builder = search_builder.filter(filter);
+        }
 
         let resp = self
             .client
@@ -149,21 +491,65 @@ impl VectorStore {
         let results = resp
             .result
             .iter()
-            .map(|pt| Self::extract_payload(&pt).unwrap())
+            .map(|pt| Self::extract_payload(pt.id.as_ref(), &pt.payload).unwrap())
             .collect();
 
         Ok(results)
     }
 
+    /// Chunks of the classes / impls enclosing `chunks` (skeletons) by point id, except the
+    /// ones already among `chunks`.
+    pub async fn parents_of(&self, chunks: &[Chunk]) -> Result<HashMap<String, Chunk>, String> {
+        let found: HashSet<&str> = chunks.iter().map(|c| c.point_id.as_str()).collect();
+        let ids: BTreeSet<&str> = chunks
+            .iter()
+            .map(|c| c.parent_id.as_str())
+            .filter(|id| !id.is_empty() && !found.contains(id))
+            .collect();
+        if ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let ids: Vec<PointId> = ids.into_iter().map(|id| id.to_string().into()).collect();
+        let resp = self
+            .client
+            .get_points(GetPointsBuilder::new(&self.collection_name, ids).with_payload(true))
+            .await
+            .map_err(|e| e.to_string())?;
+
+        Ok(resp
+            .result
+            .iter()
+            .filter_map(|pt| Self::extract_payload(pt.id.as_ref(), &pt.payload).ok())
+            .map(|chunk| (chunk.point_id.clone(), chunk))
+            .collect())
+    }
+
     pub async fn hybrid_search(
         &self,
         query_vector: &[f32],
         query_text: &str,
         top_k: u64,
+        scope: ChunkScope,
     ) -> Result<Vec<Chunk>, String> {
+        let results = self
+            .hybrid_search_scored(query_vector, query_text, top_k, scope)
+            .await?;
+        Ok(results.into_iter().map(|(chunk, _score)| chunk).collect())
+    }
+
+    /// Hybrid search keeping the RRF score of each chunk. Scores only depend on ranks,
+    /// so results of several collections can be merged by score.
+    pub async fn hybrid_search_scored(
+        &self,
+        query_vector: &[f32],
+        query_text: &str,
+        top_k: u64,
+        scope: ChunkScope,
+    ) -> Result<Vec<(Chunk, f32)>, String> {
         let (semantic_results, lexical_results) = tokio::join!(
-            self.search_top_k(query_vector, top_k * 2),
-            self.lexical_search(query_text, top_k * 2)
+            self.search_top_k(query_vector, top_k * 2, scope),
+            self.lexical_search(query_text, top_k * 2, scope)
         );
         
         let semantic_results = semantic_results?;
@@ -174,8 +560,23 @@ impl VectorStore {
         Ok(fused_results)
     }
 
-    async fn lexical_search(&self, query_text: &str, top_k: u64) -> Result<Vec<Chunk>, String> {
-        use qdrant_client::qdrant::{Filter, Condition, FieldCondition, Match};
+    /// Restrict a search to doc chunks, or to everything else.
+    fn scope_filter(scope: ChunkScope) -> Option<Filter> {
+        let is_doc = Condition::matches("chunk_type", "Doc".to_string());
+        match scope {
+            ChunkScope::All => None,
+            ChunkScope::Code => Some(Filter::must_not([is_doc])),
+            ChunkScope::Docs => Some(Filter::must([is_doc])),
+        }
+    }
+
+    async fn lexical_search(
+        &self,
+        query_text: &str,
+        top_k: u64,
+        scope: ChunkScope,
+    ) -> Result<Vec<Chunk>, String> {
+        use qdrant_client::qdrant::{FieldCondition, Match};
         
         let query_lower = query_text.to_lowercase();
         let query_terms: Vec<&str> = query_lower
@@ -214,10 +615,11 @@ impl VectorStore {
             });
         }
 
+        let scope_filter = Self::scope_filter(scope).unwrap_or_default();
         let filter = Filter {
             should: conditions,
-            must: vec![],
-            must_not: vec![],
+            must: scope_filter.must,
+            must_not: scope_filter.must_not,
             min_should: None,
         };
 
@@ -236,7 +638,7 @@ impl VectorStore {
         let results = resp
             .result
             .iter()
-            .map(|pt| Self::extract_payload(&pt).unwrap())
+            .map(|pt| Self::extract_payload(pt.id.as_ref(), &pt.payload).unwrap())
             .collect();
 
         Ok(results)
@@ -247,7 +649,7 @@ impl VectorStore {
         semantic_results: Vec<Chunk>,
         lexical_results: Vec<Chunk>,
         top_k: u64,
-    ) -> Vec<Chunk> {
+    ) -> Vec<(Chunk, f32)> {
         use std::collections::HashMap;
 
         let k = std::env::var("HYBRID_SEARCH_RRF_K")
@@ -259,7 +661,7 @@ impl VectorStore {
         let mut all_chunks: HashMap<String, Chunk> = HashMap::new();
 
         for (rank, chunk) in semantic_results.iter().enumerate() {
-            let chunk_id = format!("{}:{}", chunk.path, chunk.chunk_index);
+            let chunk_id = Self::fusion_key(chunk);
             let rrf_score = 1.0 / (k + (rank + 1) as f32);
             
             *chunk_scores.entry(chunk_id.clone()).or_insert(0.0) += rrf_score;
@@ -267,7 +669,7 @@ impl VectorStore {
         }
 
         for (rank, chunk) in lexical_results.iter().enumerate() {
-            let chunk_id = format!("{}:{}", chunk.path, chunk.chunk_index);
+            let chunk_id = Self::fusion_key(chunk);
             let rrf_score = 1.0 / (k + (rank + 1) as f32);
             
             *chunk_scores.entry(chunk_id.clone()).or_insert(0.0) += rrf_score;
@@ -283,15 +685,23 @@ impl VectorStore {
 
         scored_chunks.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
 
+        let mut scored_chunks = collapse_near_duplicates(scored_chunks);
+        scored_chunks.truncate(top_k as usize);
         scored_chunks
-            .into_iter()
-            .take(top_k as usize)
-            .map(|(chunk, _score)| chunk)
-            .collect()
     }
 
-    fn extract_payload(pt: &qdrant_client::qdrant::ScoredPoint) -> Result<Chunk, &'static str> {
-        let payload = &pt.payload;
+    /// The same text found by both searches, or stored twice, is scored once.
+    fn fusion_key(chunk: &Chunk) -> String {
+        match chunk.content_hash.as_str() {
+            "" => format!("{}:{}", chunk.path, chunk.chunk_index),
+            hash => hash.to_string(),
+        }
+    }
+
+    fn extract_payload(
+        id: Option<&PointId>,
+        payload: &HashMap<String, qdrant_client::qdrant::Value>,
+    ) -> Result<Chunk, &'static str> {
 
         let path = payload
             .get("path")
@@ -351,22 +761,45 @@ impl VectorStore {
         let chunk_type = payload
             .get("chunk_type")
             .and_then(|v| v.kind.as_ref())
-            .and_then(|kind| match kind {
+            .map(|kind| match kind {
                 qdrant_client::qdrant::value::Kind::StringValue(s) => {
                     match s.as_str() {
-                        "Function" => Some(crate::types::ChunkType::Function),
-                        "Class" => Some(crate::types::ChunkType::Class),
-                        "Method" => Some(crate::types::ChunkType::Method),
-                        "Interface" => Some(crate::types::ChunkType::Interface),
-                        "Struct" => Some(crate::types::ChunkType::Struct),
-                        "Impl" => Some(crate::types::ChunkType::Impl),
-                        "LineChunk" | _ => Some(crate::types::ChunkType::LineChunk),
+                        "Function" => crate::types::ChunkType::Function,
+                        "Class" => crate::types::ChunkType::Class,
+                        "Method" => crate::types::ChunkType::Method,
+                        "Interface" => crate::types::ChunkType::Interface,
+                        "Struct" => crate::types::ChunkType::Struct,
+                        "Impl" => crate::types::ChunkType::Impl,
+                        "Constant" => crate::types::ChunkType::Constant,
+                        "Declaration" => crate::types::ChunkType::Declaration,
+                        "Property" => crate::types::ChunkType::Property,
+                        "Module" => crate::types::ChunkType::Module,
+                        "Trait" => crate::types::ChunkType::Trait,
+                        "Enum" => crate::types::ChunkType::Enum,
+                        "Macro" => crate::types::ChunkType::Macro,
+                        "TypeAlias" => crate::types::ChunkType::TypeAlias,
+                        "Doc" => crate::types::ChunkType::Doc,
+                        _ => crate::types::ChunkType::LineChunk,
                     }
                 },
-                _ => Some(crate::types::ChunkType::LineChunk),
+                _ => crate::types::ChunkType::LineChunk,
             })
             .unwrap_or(crate::types::ChunkType::LineChunk);
 
+        let heading_path = Self::payload_strings(payload, "heading_path");
+
+        let file_hash = Self::payload_string(payload, "file_hash").unwrap_or_default();
+        let content_hash = Self::payload_string(payload, "content_hash").unwrap_or_default();
+        let duplicates = Self::payload_locations(payload, "duplicates");
+        let linked = Self::payload_locations(payload, "linked");
+        let attributes = Self::payload_strings(payload, "attributes");
+        let parent_id = Self::payload_string(payload, "parent_id").unwrap_or_default();
+        let point_id = match id.and_then(|id| id.point_id_options.as_ref()) {
+            Some(PointIdOptions::Uuid(uuid)) => uuid.clone(),
+            Some(PointIdOptions::Num(num)) => num.to_string(),
+            None => String::new(),
+        };
+
         Ok(Chunk {
             path,
             chunk_index: chunk_index.to_string(),
@@ -375,6 +808,173 @@ impl VectorStore {
             text: chunk_text,
             function_name,
             chunk_type,
+            heading_path,
+            file_hash,
+            content_hash,
+            duplicates,
+            point_id,
+            linked,
+            attributes,
+            parent_id,
         })
     }
+
+    fn payload_strings(
+        payload: &HashMap<String, qdrant_client::qdrant::Value>,
+        key: &str,
+    ) -> Vec<String> {
+        payload
+            .get(key)
+            .and_then(|v| v.kind.as_ref())
+            .map(|kind| match kind {
+                qdrant_client::qdrant::value::Kind::ListValue(list) => list
+                    .values
+                    .iter()
+                    .filter_map(|v| match &v.kind {
+                        Some(qdrant_client::qdrant::value::Kind::StringValue(s)) => Some(s.clone()),
+                        _ => None,
+                    })
+                    .collect(),
+                _ => Vec::new(),
+            })
+            .unwrap_or_default()
+    }
+
+    fn payload_locations(
+        payload: &HashMap<String, qdrant_client::qdrant::Value>,
+        key: &str,
+    ) -> Vec<ChunkLocation> {
+        payload
+            .get(key)
+            .and_then(|v| serde_json::to_value(v).ok())
+            .and_then(|v| serde_json::from_value(v).ok())
+            .unwrap_or_default()
+    }
+
+    fn payload_string(
+        payload: &HashMap<String, qdrant_client::qdrant::Value>,
+        key: &str,
+    ) -> Option<String> {
+        payload
+            .get(key)
+            .and_then(|v| v.kind.as_ref())
+            .and_then(|kind| match kind {
+                qdrant_client::qdrant::value::Kind::StringValue(s) => Some(s.clone()),
+                _ => None,
+            })
+    }
+}
+
+/// Share of common word sequences from which two results are considered copies of the same code.
+const NEAR_DUPLICATE_SIMILARITY: f32 = 0.9;
+const SHINGLE_SIZE: usize = 3;
+
+/// Drop the results (sorted by score) that are near-identical to a better one, e.g. two
+/// versions of a vendored file, recording their location on the result kept.
+fn collapse_near_duplicates(scored_chunks: Vec<(Chunk, f32)>) -> Vec<(Chunk, f32)> {
+    let mut kept: Vec<(Chunk, f32, HashMap<String, usize>)> = Vec::new();
+    for (chunk, score) in scored_chunks {
+        let chunk_shingles = shingles(&chunk);
+        let copy_of = kept.iter_mut().find(|(other, _, other_shingles)| {
+            (!chunk.content_hash.is_empty() && chunk.content_hash == other.content_hash)
+                || similarity(&chunk_shingles, other_shingles) >= NEAR_DUPLICATE_SIMILARITY
+        });
+        match copy_of {
+            Some((other, _, _)) => {
+                let location = ChunkLocation {
+                    path: chunk.path,
+                    chunk_start_line: chunk.chunk_start_line,
+                    chunk_end_line: chunk.chunk_end_line,
+                    file_hash: chunk.file_hash,
+                };
+                for location in std::iter::once(location).chain(chunk.duplicates) {
+                    if location.path != other.path && !other.duplicates.contains(&location) {
+                        other.duplicates.push(location);
+                    }
+                }
+            }
+            None => kept.push((chunk, score, chunk_shingles)),
+        }
+    }
+    kept.into_iter().map(|(chunk, score, _)| (chunk, score)).collect()
+}
+
+/// Sequences of `SHINGLE_SIZE` consecutive words of the chunk, counted: unlike a set of words,
+/// they tell apart code that uses the same words in another order.
+fn shingles(chunk: &Chunk) -> HashMap<String, usize> {
+    // Le texte sans son en-tête, qui contient le chemin
+    let body = chunk.text.split_once('\n').map_or(chunk.text.as_str(), |(_, body)| body);
+    let words: Vec<&str> = body
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .filter(|word| !word.is_empty())
+        .collect();
+
+    let mut shingles = HashMap::new();
+    for shingle in words.windows(SHINGLE_SIZE.min(words.len()).max(1)) {
+        *shingles.entry(shingle.join(" ")).or_default() += 1;
+    }
+    shingles
+}
+
+/// Weighted Jaccard index of two shingle counts.
+fn similarity(a: &HashMap<String, usize>, b: &HashMap<String, usize>) -> f32 {
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    let (mut shared, mut total) = (0, 0);
+    for shingle in a.keys().chain(b.keys().filter(|k| !a.contains_key(*k))) {
+        let (x, y) = (a.get(shingle).copied().unwrap_or(0), b.get(shingle).copied().unwrap_or(0));
+        shared += x.min(y);
+        total += x.max(y);
+    }
+    shared as f32 / total as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ChunkType;
+
+    fn chunk(path: &str, text: &str, content_hash: &str) -> Chunk {
+        Chunk {
+            path: path.to_string(),
+            chunk_index: "0".to_string(),
+            chunk_start_line: 1,
+            chunk_end_line: 3,
+            text: format!("[Function] parse @ {path}:1-3\n{text}"),
+            function_name: Some("parse".to_string()),
+            chunk_type: ChunkType::Function,
+            content_hash: content_hash.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_collapse_near_duplicates() {
+        let parse = "fn parse(input: &str) -> Result<Ast, Error> { let tokens = lex(input)?; build_tree(tokens, Mode::Strict) }";
+        let results = vec![
+            (chunk("clone/app/src/parse.rs", parse, "a"), 0.03),
+            (chunk("clone/app/vendor/parser/parse.rs", parse, "a"), 0.02),
+            // Même code, un mot de plus
+            (chunk("clone/app/vendor/parser-old/parse.rs", &format!("{parse} // legacy"), "b"), 0.015),
+            (chunk("clone/app/src/main.rs", "fn main() { run(parse(&args)) }", "c"), 0.01),
+        ];
+
+        let collapsed = collapse_near_duplicates(results);
+        let paths: Vec<_> = collapsed.iter().map(|(c, _)| c.path.as_str()).collect();
+        assert_eq!(paths, vec!["clone/app/src/parse.rs", "clone/app/src/main.rs"]);
+        let copies: Vec<_> = collapsed[0].0.duplicates.iter().map(|l| l.path.as_str()).collect();
+        assert_eq!(copies, vec!["clone/app/vendor/parser/parse.rs", "clone/app/vendor/parser-old/parse.rs"]);
+    }
+
+    #[test]
+    fn test_same_words_in_another_order_are_kept() {
+        let grant = "if user.is_admin() { grant(access) } else { deny(access) }";
+        let deny = "if user.is_admin() { deny(access) } else { grant(access) }";
+        let results = vec![
+            (chunk("clone/app/src/auth.rs", grant, "a"), 0.03),
+            (chunk("clone/app/src/guard.rs", deny, "b"), 0.02),
+        ];
+        assert_eq!(collapse_near_duplicates(results).len(), 2);
+    }
 }
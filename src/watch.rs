@@ -0,0 +1,97 @@
+use crate::jobs::ScanProgress;
+use crate::types::{FilterConfig, ScanMode};
+use notify::{RecursiveMode, Watcher};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// Keep the index of the working tree of `clone/<repo_name>` live: an incremental scan first,
+/// then the files touched by each burst of changes are re-indexed once nothing changed for
+/// `WATCH_DEBOUNCE_MS`. `start` registers each update as a job; while it fails (e.g. a scan of the
+/// repo is running) the changes are kept for the next attempt. Runs until dropped.
+pub async fn watch_repo(
+    repo_name: String,
+    filter_config: Option<FilterConfig>,
+    start: impl Fn() -> Result<ScanProgress, String>,
+) -> Result<(), String> {
+    let debounce = Duration::from_millis(crate::config::Config::from_env().watch_debounce_ms);
+    let root = std::fs::canonicalize(format!("clone/{repo_name}"))
+        .map_err(|e| format!("clone/{repo_name}: {e}"))?;
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        match event {
+            Ok(event) if !event.kind.is_access() => {
+                let _ = tx.send(event.paths);
+            }
+            Ok(_) => (),
+            Err(e) => eprintln!("Watch error: {e}"),
+        }
+    })
+    .map_err(|e| format!("Unable to watch {}: {e}", root.display()))?;
+    watcher
+        .watch(&root, RecursiveMode::Recursive)
+        .map_err(|e| format!("Unable to watch {}: {e}", root.display()))?;
+    println!("Watching {} for changes", root.display());
+
+    // Le watcher tourne déjà : rien de ce qui change pendant ce scan n'est perdu
+    match start() {
+        Ok(progress) => {
+            let synced = crate::scan_repo(
+                repo_name.clone(),
+                None,
+                filter_config.clone(),
+                ScanMode::Incremental,
+                &progress,
+            )
+            .await;
+            if let Err(e) = synced {
+                eprintln!("Initial scan of {repo_name} failed: {e}");
+            }
+        }
+        Err(e) => eprintln!("Initial scan of {repo_name} skipped: {e}"),
+    }
+
+    let mut touched = BTreeSet::new();
+    loop {
+        if touched.is_empty() {
+            let paths = rx.recv().await.ok_or("File watcher stopped")?;
+            collect_paths(&root, paths, &mut touched);
+        }
+        // Attend que le dépôt reste calme pendant `debounce`
+        while let Ok(paths) = tokio::time::timeout(debounce, rx.recv()).await {
+            collect_paths(&root, paths.ok_or("File watcher stopped")?, &mut touched);
+        }
+        if touched.is_empty() {
+            continue;
+        }
+
+        let progress = match start() {
+            Ok(progress) => progress,
+            Err(e) => {
+                eprintln!("Update of {repo_name} postponed: {e}");
+                continue;
+            }
+        };
+        let files: Vec<String> = std::mem::take(&mut touched).into_iter().collect();
+        if let Err(e) = crate::update_files(&repo_name, &files, filter_config.clone(), &progress).await {
+            eprintln!("Update of {repo_name} failed: {e}");
+        }
+    }
+}
+
+/// Keep the paths relative to the repo root, ignoring the `.git` directory.
+fn collect_paths(root: &Path, paths: Vec<PathBuf>, touched: &mut BTreeSet<String>) {
+    for path in paths {
+        let Ok(relative) = path.strip_prefix(root) else {
+            continue;
+        };
+        if relative.components().next().is_none_or(|c| c.as_os_str() == ".git") {
+            continue;
+        }
+        if let Some(relative) = relative.to_str() {
+            touched.insert(relative.to_string());
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use crate::types::Chunk;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+
+pub const WORKSPACES_PATH: &str = "generated/workspaces.json";
+
+/// Named group of indexed repos (collection names, e.g. `backend` or `shared@v2`)
+/// that are questioned together.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Workspace {
+    pub name: String,
+    pub repos: Vec<String>,
+}
+
+// Sérialise les lecture-modification-écriture du fichier
+static WRITE_LOCK: Mutex<()> = Mutex::new(());
+
+pub fn list(path: &str) -> Result<Vec<Workspace>, String> {
+    if !Path::new(path).exists() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(path).map_err(|e| format!("Error reading {path}: {e}"))?;
+    serde_json::from_str(&content).map_err(|e| format!("Error decoding {path}: {e}"))
+}
+
+pub fn get(path: &str, name: &str) -> Result<Workspace, String> {
+    list(path)?
+        .into_iter()
+        .find(|w| w.name == name)
+        .ok_or_else(|| format!("No workspace {name}"))
+}
+
+/// Create the workspace or replace its repos.
+pub fn save(path: &str, name: &str, repos: Vec<String>) -> Result<Workspace, String> {
+    if !crate::git::is_valid_repo_name(name) {
+        return Err(format!("Invalid workspace name {name}"));
+    }
+    let mut members: Vec<String> = Vec::new();
+    for repo in repos {
+        let repo = repo.trim().to_string();
+        if !repo.is_empty() && !members.contains(&repo) {
+            members.push(repo);
+        }
+    }
+    if members.is_empty() {
+        return Err(format!("Workspace {name} needs at least one repo"));
+    }
+
+    let _guard = WRITE_LOCK.lock().unwrap();
+    let workspace = Workspace {
+        name: name.to_string(),
+        repos: members,
+    };
+    let mut workspaces = list(path)?;
+    workspaces.retain(|w| w.name != name);
+    workspaces.push(workspace.clone());
+    workspaces.sort_by(|a, b| a.name.cmp(&b.name));
+    write(path, &workspaces)?;
+    Ok(workspace)
+}
+
+/// False when there was no such workspace.
+pub fn remove(path: &str, name: &str) -> Result<bool, String> {
+    let _guard = WRITE_LOCK.lock().unwrap();
+    let mut workspaces = list(path)?;
+    let count = workspaces.len();
+    workspaces.retain(|w| w.name != name);
+    if workspaces.len() == count {
+        return Ok(false);
+    }
+    write(path, &workspaces)?;
+    Ok(true)
+}
+
+/// Merge the scored results of every member repo and keep the `top_k` best,
+/// each labelled with the repo it comes from.
+pub fn fuse(results: Vec<(String, Vec<(Chunk, f32)>)>, top_k: usize) -> Vec<(String, Chunk)> {
+    let mut merged: Vec<(String, Chunk, f32)> = results
+        .into_iter()
+        .flat_map(|(repo, chunks)| {
+            chunks
+                .into_iter()
+                .map(move |(chunk, score)| (repo.clone(), chunk, score))
+        })
+        .collect();
+    merged.sort_by(|a, b| b.2.partial_cmp(&a.2).unwrap_or(std::cmp::Ordering::Equal));
+    merged
+        .into_iter()
+        .take(top_k)
+        .map(|(repo, chunk, _score)| (repo, chunk))
+        .collect()
+}
+
+/// Path of a chunk inside its repo: `clone/backend/src/main.rs` → `src/main.rs`.
+pub fn repo_relative_path(path: &str) -> &str {
+    path.strip_prefix("clone/")
+        .and_then(|p| p.split_once('/'))
+        .map_or(path, |(_, relative)| relative)
+}
+
+/// Write to a temporary file then rename, like the checkpoints.
+fn write(path: &str, workspaces: &[Workspace]) -> Result<(), String> {
+    if let Some(dir) = Path::new(path).parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Error creating {}: {e}", dir.display()))?;
+    }
+    let json = serde_json::to_vec_pretty(workspaces).map_err(|e| e.to_string())?;
+    let tmp = format!("{path}.tmp");
+    fs::write(&tmp, json).map_err(|e| format!("Error writing {tmp}: {e}"))?;
+    fs::rename(&tmp, path).map_err(|e| format!("Error writing {path}: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_list_remove() {
+        let dir = std::env::temp_dir().join(format!("bdw-workspaces-{}", std::process::id()));
+        let path = dir.join("workspaces.json");
+        let path = path.to_str().unwrap();
+
+        assert!(list(path).unwrap().is_empty());
+        let repos = vec!["backend".into(), " frontend ".into(), "backend".into()];
+        let saved = save(path, "product", repos).unwrap();
+        assert_eq!(saved.repos, vec!["backend", "frontend"]);
+        save(path, "libs", vec!["shared@v2".into()]).unwrap();
+        save(path, "product", vec!["backend".into()]).unwrap();
+
+        let names: Vec<_> = list(path).unwrap().into_iter().map(|w| w.name).collect();
+        assert_eq!(names, vec!["libs", "product"]);
+        assert_eq!(get(path, "product").unwrap().repos, vec!["backend"]);
+        assert!(save(path, "empty", vec![" ".into()]).is_err());
+        assert!(save(path, "a/b", vec!["backend".into()]).is_err());
+
+        assert!(remove(path, "libs").unwrap());
+        assert!(!remove(path, "libs").unwrap());
+        assert!(get(path, "libs").is_err());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_fuse_keeps_best_scores_across_repos() {
+        let chunk = |path: &str| Chunk {
+            path: path.to_string(),
+            chunk_index: "0".to_string(),
+            chunk_start_line: 1,
+            chunk_end_line: 2,
+            text: String::new(),
+            chunk_type: crate::types::ChunkType::Function,
+            ..Default::default()
+        };
+        let results = vec![
+            ("backend".to_string(), vec![(chunk("clone/backend/a.rs"), 0.03), (chunk("clone/backend/b.rs"), 0.01)]),
+            ("frontend".to_string(), vec![(chunk("clone/frontend/c.ts"), 0.02)]),
+        ];
+
+        let fused: Vec<_> = fuse(results, 2)
+            .into_iter()
+            .map(|(repo, c)| format!("{repo}:{}", repo_relative_path(&c.path)))
+            .collect();
+        assert_eq!(fused, vec!["backend:a.rs", "frontend:c.ts"]);
+    }
+}